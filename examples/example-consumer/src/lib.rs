@@ -7,9 +7,13 @@ use socrates::service::Svc;
 
 use socrates::Result;
 
+socrates::declare_module! {
+    manifest: include_str!("../manifest.json"),
+    activate: activate,
+}
+
 use example_api::greet::{GreetRequest, Greeter, Idiom};
 
-#[no_mangle]
 fn activate(ctx: Context) -> Result<Box<dyn Activator>> {
     println!("I'm started (consumer)");
     // panic!("shoudln't segfault!");
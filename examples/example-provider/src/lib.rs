@@ -6,7 +6,11 @@ use socrates::module::{Activator, Context};
 use socrates::service::ServiceRegistration;
 use socrates::Result;
 
-#[no_mangle]
+socrates::declare_module! {
+    manifest: include_str!("../manifest.json"),
+    activate: activate,
+}
+
 fn activate(ctx: Context) -> Result<Box<Activator>> {
     println!("I'm started (provider)");
     println!(
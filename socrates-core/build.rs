@@ -0,0 +1,24 @@
+use std::env;
+use std::process::Command;
+
+// Records what the ABI of a module built against this crate depends on, see `module::AbiFingerprint`.
+fn main() {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let rustc_version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_owned())
+        .unwrap_or_else(|| "unknown".to_owned());
+    println!("cargo:rustc-env=SOCRATES_RUSTC_VERSION={}", rustc_version);
+
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase())
+        })
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=SOCRATES_FEATURES={}", features.join(","));
+}
@@ -1,3 +1,5 @@
+use super::catch_panic;
+use crate::module::DynamodId;
 use std::ops::Deref;
 use std::sync::{Arc, Weak};
 
@@ -56,3 +58,76 @@ impl<E> WeakListener<E> {
         }
     }
 }
+
+/// Listeners that panicked, with their owner and the panic message.
+pub type ListenerPanics = Vec<(Option<DynamodId>, String)>;
+
+// Listeners registered by a module are tagged with its id, host listeners aren't.
+#[derive(Clone)]
+pub struct Listeners<E> {
+    listeners: Vec<(Option<DynamodId>, WeakListener<E>)>,
+}
+
+impl<E: Clone> Default for Listeners<E> {
+    fn default() -> Listeners<E> {
+        Listeners {
+            listeners: Vec::new(),
+        }
+    }
+}
+
+impl<E: Clone> Listeners<E> {
+    pub fn insert_listener(&mut self, listener: WeakListener<E>, owner: Option<DynamodId>) {
+        self.clean_up();
+        self.listeners.push((owner, listener));
+    }
+
+    pub fn clean_up(&mut self) {
+        self.listeners.retain(|(_, listener)| listener.is_alive());
+    }
+
+    /// Removes the listeners registered by `owner`, returns how many were still alive.
+    pub fn remove_owned_by(&mut self, owner: DynamodId) -> usize {
+        let mut alive = 0;
+        self.listeners.retain(|(listener_owner, listener)| {
+            if *listener_owner != Some(owner) {
+                return true;
+            }
+            if listener.is_alive() {
+                alive += 1;
+            }
+            false
+        });
+        alive
+    }
+
+    // returns dirty if it should be cleaned, and the listeners that panicked.
+    // A panicking listener doesn't prevent the others from getting the event.
+    pub fn fire_event(&self, event: &E) -> (bool, ListenerPanics) {
+        self.fire_event_to(event, |_| true)
+    }
+
+    /// Like `fire_event`, but only to the listeners whose owner is accepted.
+    pub fn fire_event_to<F: Fn(Option<DynamodId>) -> bool>(
+        &self,
+        event: &E,
+        accept: F,
+    ) -> (bool, ListenerPanics) {
+        let mut dirty = false;
+        let mut panics = Vec::new();
+        for (owner, listener) in self.listeners.iter() {
+            if !accept(*owner) {
+                continue;
+            }
+            match catch_panic(|| listener.fire_event(event)) {
+                Ok(was_fired) => {
+                    if !was_fired {
+                        dirty = true;
+                    }
+                }
+                Err(message) => panics.push((*owner, message)),
+            }
+        }
+        (dirty, panics)
+    }
+}
@@ -1,5 +1,9 @@
 pub mod listener;
+pub mod panic;
 
 pub use self::listener::EventListener;
 pub use self::listener::Listener;
+pub use self::listener::ListenerPanics;
+pub use self::listener::Listeners;
 pub use self::listener::WeakListener;
+pub use self::panic::catch_panic;
@@ -113,6 +113,7 @@ impl EventListener<ServiceEvent> for ComponentManager {
     }
 }
 
+use log::{debug, trace};
 use parking_lot::RwLock;
 use std::sync::Arc;
 
@@ -250,7 +251,14 @@ impl<T: Component> ComponentController<T> {
     }
 
     fn handle_change(&self, was_satisfied: bool, is_satisfied: bool, _old: ComponentReferences) {
-        println!("A CHANGE HAPPENED!");
+        debug!(
+            component = self.definition.name.as_str(),
+            module_id = self.module_id(),
+            was_satisfied,
+            is_satisfied;
+            "Component {} changed",
+            self.definition.name
+        );
         self.print_status();
         if is_satisfied {
             if was_satisfied {
@@ -272,28 +280,42 @@ impl<T: Component> ComponentController<T> {
     fn update(&self) {}
 
     fn instantiate(&self) {
-        if let Some(component) =
-            (self.instantiate)(self.context.as_ref().unwrap(), &self.references.read())
-        {
+        let context = self.context.as_ref().unwrap();
+        // A panicking component fails its module instead of whoever fired the event.
+        let instantiated = catch_panic(|| {
+            let component = (self.instantiate)(context, &self.references.read())?;
             component
-                .update(0, self.context.as_ref().unwrap(), &self.references.read())
+                .update(0, context, &self.references.read())
                 .unwrap();
-            let ci = ComponentInstance::new(None, component);
-            let mut instances = self.instances.write();
-            instances.push(ci);
+            Some(component)
+        });
+
+        match instantiated {
+            Ok(Some(component)) => {
+                let ci = ComponentInstance::new(None, component);
+                let mut instances = self.instances.write();
+                instances.push(ci);
+            }
+            Ok(None) => (),
+            Err(message) => context.fail(format!(
+                "Panic instantiating component {}: {}",
+                self.definition.name, message
+            )),
         }
     }
     fn drop(&self) {
         self.instances.write().clear();
     }
 
+    fn module_id(&self) -> Option<DynamodId> {
+        self.context.as_ref().map(ModuleContext::get_id)
+    }
+
     fn print_status(&self) {
-        println!(
-            r#"
-        #######################
-        Satisfied: {:?},
-        references: {:?}
-        "#,
+        trace!(
+            component = self.definition.name.as_str(),
+            module_id = self.module_id();
+            "Satisfied: {:?}, references: {:?}",
             self.is_satisfied(),
             *(self.references.read())
         );
@@ -3,10 +3,34 @@ pub mod component;
 pub mod module;
 pub mod service;
 
+// Re-exported for generated code and modules, so that they log through the host's logger.
+pub use log;
+
+use self::module::{Abi, DynamodId, DynamodState, Permission, VerificationError};
+
 #[derive(Debug)]
 pub enum Error {
     StrError(String),
     IoError(std::io::Error),
+    ModuleNotFound(DynamodId),
+    InvalidModuleState(DynamodId, DynamodState),
+    InvalidManifest(String),
+    InvalidContainerState(String),
+    Unresolved(DynamodId, Vec<String>),
+    ModuleInUse(DynamodId, Vec<DynamodId>),
+    Panic(String),
+    ModuleFailed(DynamodId, String),
+    StopTimeout(DynamodId),
+    PermissionDenied(DynamodId, Permission, String),
+    VerificationFailed(String, VerificationError),
+    IncompatibleDeclaration {
+        expected: u32,
+        found: Option<u32>,
+    },
+    IncompatibleAbi {
+        expected: Box<Abi>,
+        found: Option<Box<Abi>>,
+    },
 }
 
 impl From<&str> for Error {
@@ -0,0 +1,108 @@
+use super::*;
+
+/// A `&'static str` with a stable layout, to be read from a module's shared object.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct FfiStr {
+    ptr: *const u8,
+    len: usize,
+}
+
+unsafe impl Send for FfiStr {}
+unsafe impl Sync for FfiStr {}
+
+impl FfiStr {
+    pub const fn new(s: &'static str) -> FfiStr {
+        FfiStr {
+            ptr: s.as_ptr(),
+            len: s.len(),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Only valid while the shared object holding the string is loaded.
+    pub unsafe fn as_str(&self) -> std::result::Result<&str, std::str::Utf8Error> {
+        std::str::from_utf8(std::slice::from_raw_parts(self.ptr, self.len))
+    }
+}
+
+/// A `'static` slice with a stable layout.
+#[repr(C)]
+pub struct FfiSlice<T: 'static> {
+    ptr: *const T,
+    len: usize,
+}
+
+unsafe impl<T: Sync> Send for FfiSlice<T> {}
+unsafe impl<T: Sync> Sync for FfiSlice<T> {}
+
+impl<T> FfiSlice<T> {
+    pub const fn new(s: &'static [T]) -> FfiSlice<T> {
+        FfiSlice {
+            ptr: s.as_ptr(),
+            len: s.len(),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Only valid while the shared object holding the slice is loaded.
+    pub unsafe fn as_slice(&self) -> &[T] {
+        std::slice::from_raw_parts(self.ptr, self.len)
+    }
+}
+
+/// Everything that must match between the container and a module for them to share
+/// trait objects, `TypeId`s and allocations.
+#[repr(C)]
+pub struct AbiFingerprint {
+    rustc: FfiStr,
+    core_version: FfiStr,
+    features: FfiStr,
+}
+
+impl AbiFingerprint {
+    pub const CURRENT: AbiFingerprint = AbiFingerprint {
+        rustc: FfiStr::new(env!("SOCRATES_RUSTC_VERSION")),
+        core_version: FfiStr::new(env!("CARGO_PKG_VERSION")),
+        features: FfiStr::new(env!("SOCRATES_FEATURES")),
+    };
+
+    unsafe fn to_abi(&self) -> Option<Abi> {
+        Some(Abi {
+            rustc: self.rustc.as_str().ok()?.to_owned(),
+            core_version: self.core_version.as_str().ok()?.to_owned(),
+            features: self.features.as_str().ok()?.to_owned(),
+        })
+    }
+}
+
+/// An owned copy of an `AbiFingerprint`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Abi {
+    pub rustc: String,
+    pub core_version: String,
+    pub features: String,
+}
+
+impl Abi {
+    pub fn current() -> Abi {
+        unsafe { AbiFingerprint::CURRENT.to_abi() }.expect("invalid ABI fingerprint")
+    }
+
+    /// Refuses any module fingerprint that differs from the container's.
+    pub fn check(fingerprint: &AbiFingerprint) -> Result<()> {
+        let expected = Abi::current();
+        let found = unsafe { fingerprint.to_abi() };
+
+        if found.as_ref() == Some(&expected) {
+            Ok(())
+        } else {
+            Err(Error::IncompatibleAbi {
+                expected: Box::new(expected),
+                found: found.map(Box::new),
+            })
+        }
+    }
+}
@@ -1,5 +1,19 @@
 use super::*;
 
 pub type ActivateFn = fn(Context) -> Result<Box<dyn Activator>>;
+pub type DeactivateFn = fn(Box<dyn Activator>) -> Result<()>;
 
-pub trait Activator: Send {}
+/// Made by the module's activate function, it lives as long as the module is active.
+pub trait Activator: Send {
+    /// Called when the module stops, before the deactivate function. An error fails the module.
+    fn stop(&mut self, _ctx: &Context) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Used when a module doesn't declare a deactivate function: dropping the activator releases
+/// everything it holds.
+pub fn default_deactivate(activator: Box<dyn Activator>) -> Result<()> {
+    drop(activator);
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use super::*;
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Extension of module archives.
+pub const ARCHIVE_EXTENSION: &str = "smod";
+
+const MANIFEST_FILE: &str = "manifest.json";
+const RESOURCES_DIR: &str = "resources";
+
+/// A packaged module: a tar archive holding one library at its root, optionally a
+/// `manifest.json` and a `resources` directory.
+///
+/// The manifest of the archive replaces the one embedded in the library, which must then
+/// declare a single module. Archives are extracted into a cache directory, each install in
+/// its own private directory, removed once the library is unloaded.
+pub struct ModuleArchive {
+    pub dir: PathBuf,
+    pub library: PathBuf,
+    pub manifest: Option<Manifest>,
+}
+
+impl ModuleArchive {
+    pub fn is_archive(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == ARCHIVE_EXTENSION)
+    }
+
+    /// Extracts `bytes`, the content of the archive at `path`.
+    pub fn extract(path: &Path, bytes: &[u8], cache_dir: &Path) -> Result<ModuleArchive> {
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let dir = DynamodLib::create_private_dir(cache_dir, &format!("socrates-{}", stem))?;
+
+        let extracted = ModuleArchive::extract_into(path, bytes, &dir);
+        if extracted.is_err() {
+            let _ = fs::remove_dir_all(&dir);
+        }
+        extracted
+    }
+
+    fn extract_into(path: &Path, bytes: &[u8], dir: &Path) -> Result<ModuleArchive> {
+        // Entries that would land outside of `dir` are skipped.
+        tar::Archive::new(bytes).unpack(dir)?;
+
+        let mut libraries = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry_path = entry?.path();
+            let is_library = entry_path
+                .extension()
+                .is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION);
+            // A symbolic link could point to a library that wasn't verified.
+            if is_library && fs::symlink_metadata(&entry_path)?.is_file() {
+                libraries.push(entry_path);
+            }
+        }
+        if libraries.len() != 1 {
+            return Err(Error::InvalidManifest(format!(
+                "{} must hold exactly one library, found {}",
+                path.display(),
+                libraries.len()
+            )));
+        }
+
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let is_manifest = fs::symlink_metadata(&manifest_path).is_ok_and(|m| m.is_file());
+        let manifest = if is_manifest {
+            Some(Manifest::from_json(&fs::read_to_string(manifest_path)?)?)
+        } else {
+            None
+        };
+
+        Ok(ModuleArchive {
+            dir: dir.to_owned(),
+            library: libraries.remove(0),
+            manifest,
+        })
+    }
+
+    /// The path of a resource in an extracted archive, refusing paths that leave its resources.
+    pub fn resource_path(dir: &Path, resource: &str) -> Result<PathBuf> {
+        let resource = Path::new(resource);
+        let is_contained = resource
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !is_contained {
+            return Err(format!("Invalid resource path: {}", resource.display()).into());
+        }
+
+        Ok(dir.join(RESOURCES_DIR).join(resource))
+    }
+}
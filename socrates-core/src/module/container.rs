@@ -1,10 +1,28 @@
 use super::*;
 
-#[derive(Default)]
+use log::warn;
+use std::path::PathBuf;
+use std::time::Duration;
+
 pub struct Container {
+    watcher: Mutex<Option<Watcher>>, // must be first to be stopped first
+    deployer: Mutex<Option<Deployer>>, // stopped before the modules too
     svc_manager: Arc<ServiceManager>, // the "master" strong ref
-    modules: Mutex<Vec<Dynamod>>,
-    zombie_modules: Mutex<Vec<Dynamod>>,
+    mod_manager: Arc<ModuleManager>, // the "master" strong ref
+}
+
+impl Default for Container {
+    fn default() -> Container {
+        let svc_manager = Arc::new(ServiceManager::default());
+        let mod_manager = ModuleManager::new(Arc::downgrade(&svc_manager));
+        svc_manager.set_module_manager(Arc::downgrade(&mod_manager));
+        Container {
+            watcher: Mutex::new(None),
+            deployer: Mutex::new(None),
+            svc_manager,
+            mod_manager,
+        }
+    }
 }
 
 impl Container {
@@ -12,59 +30,255 @@ impl Container {
         Default::default()
     }
 
-    fn shared_service_manager(&self) -> Weak<ServiceManager> {
-        Arc::downgrade(&self.svc_manager)
+    /// Installs every module declared by the library or module archive at `path`,
+    /// returns their ids. Modules missing a requirement are still installed, a
+    /// `ModuleUnresolved` event tells what they miss, as does `resolve`.
+    pub fn install(&self, path: &str) -> Result<Vec<DynamodId>> {
+        self.mod_manager.install(path)
     }
 
-    pub fn install(&self, path: &str) -> Result<()> {
-        let mut mods = self.modules.lock();
-        let id = (*mods).len() as u32;
-        let lib = libloading::Library::new(path)?;
+    /// Installs a module linked into the host from its activate function, without any library.
+    /// Its id comes from the `FIRST_STATIC_ID` range, apart from the ids of saved modules.
+    pub fn install_static(&self, manifest: Manifest, activate: ActivateFn) -> DynamodId {
+        let vtable = ModuleVTable {
+            activate,
+            deactivate: default_deactivate,
+            init_log,
+        };
+        self.mod_manager.install_static(manifest, vtable)
+    }
 
-        let dyn_mod = Dynamod::new(id, self.shared_service_manager(), path, lib);
-        mods.push(dyn_mod);
+    /// Uninstalls the module, returns the modules that were using its services:
+    /// they keep references into a zombie until they are refreshed.
+    pub fn uninstall(&self, id: DynamodId) -> Result<Vec<DynamodId>> {
+        self.mod_manager.uninstall(id)
+    }
 
-        Ok(())
+    /// Uninstalls the module only if no other module uses its services,
+    /// fails with `ModuleInUse` otherwise.
+    pub fn uninstall_if_unused(&self, id: DynamodId) -> Result<()> {
+        self.mod_manager.uninstall_if_unused(id)
+    }
+
+    pub fn get_dependents(&self, id: DynamodId) -> Result<Vec<DynamodId>> {
+        self.mod_manager.get_dependents(id)
+    }
+
+    /// Restarts the modules that hold references into zombies, see `get_zombies`.
+    pub fn refresh(&self) -> Result<Vec<DynamodId>> {
+        self.mod_manager.refresh()
+    }
+
+    pub fn start(&self, id: DynamodId) -> Result<()> {
+        self.mod_manager.start(id)
+    }
+
+    pub fn stop(&self, id: DynamodId) -> Result<()> {
+        self.mod_manager.stop(id)
+    }
+
+    pub fn reload(&self, id: DynamodId) -> Result<()> {
+        self.mod_manager.reload(id)
+    }
+
+    /// Replaces the module's library with the one at `path`, keeping its id and
+    /// restarting it if it was active.
+    pub fn update(&self, id: DynamodId, path: &str) -> Result<()> {
+        self.mod_manager.update(id, path)
+    }
+
+    pub fn get_data_root(&self) -> Option<PathBuf> {
+        self.mod_manager.get_data_root()
+    }
+
+    /// Root of the modules' data directories, see `Context::data_dir`.
+    pub fn set_data_root(&self, data_root: &str) {
+        self.mod_manager
+            .set_data_root(Some(PathBuf::from(data_root)))
+    }
+
+    pub fn get_cache_dir(&self) -> Option<PathBuf> {
+        self.mod_manager.get_cache_dir()
+    }
+
+    /// Where module archives are extracted when they are installed, it should only be writable
+    /// by the host.
+    pub fn set_cache_dir(&self, cache_dir: &str) {
+        self.mod_manager
+            .set_cache_dir(Some(PathBuf::from(cache_dir)))
+    }
+
+    pub fn get_trust_policy(&self) -> TrustPolicy {
+        self.mod_manager.get_trust_policy()
+    }
+
+    /// Verifies module files before loading them, installs and updates of modules that
+    /// fail verification are refused with `Error::VerificationFailed`.
+    pub fn set_trust_policy(&self, trust_policy: TrustPolicy) {
+        self.mod_manager.set_trust_policy(trust_policy)
+    }
+
+    pub fn get_permission_policy(&self) -> PermissionPolicy {
+        self.mod_manager.get_permission_policy()
+    }
+
+    /// Restricts the services modules can register, get and listen to, on top of the permissions
+    /// they declare in their manifest. Applies to the modules that start afterwards.
+    pub fn set_permission_policy(&self, policy: PermissionPolicy) {
+        self.mod_manager.set_permission_policy(policy)
+    }
+
+    pub fn get_stop_timeout(&self) -> Option<Duration> {
+        self.mod_manager.get_stop_timeout()
+    }
+
+    /// Modules that take longer than `stop_timeout` to stop are abandoned: they stay `Stopping`,
+    /// and can't be started again, until their stop returns, then they fail.
+    /// `None` (the default) waits for them.
+    pub fn set_stop_timeout(&self, stop_timeout: Option<Duration>) {
+        self.mod_manager.set_stop_timeout(stop_timeout)
+    }
+
+    pub fn get_start_level(&self) -> StartLevel {
+        self.mod_manager.get_start_level()
+    }
+
+    /// Stops the active modules above `start_level`, and starts the ones up to it.
+    pub fn set_start_level(&self, start_level: StartLevel) -> Result<()> {
+        self.mod_manager.set_start_level(start_level)
     }
 
-    pub fn uninstall(&self, idx: DynamodId) -> Result<()> {
-        let mut mods = self.modules.lock();
-        let dyn_mod = mods.remove(idx as usize);
+    pub fn get_module_start_level(&self, id: DynamodId) -> Result<StartLevel> {
+        self.mod_manager.get_module_start_level(id)
+    }
+
+    pub fn set_module_start_level(&self, id: DynamodId, start_level: StartLevel) -> Result<()> {
+        self.mod_manager.set_module_start_level(id, start_level)
+    }
+
+    /// Starts the resolved modules, but not the lazy ones: they start on the first lookup
+    /// by name of a service they provide, see `Activation::Lazy`.
+    pub fn start_all(&self) -> Result<()> {
+        self.mod_manager.start_all()
+    }
 
-        {
-            let mut zombie_mods = self.zombie_modules.lock();
-            let zm = dyn_mod.zombify();
+    pub fn stop_all(&self) -> Result<()> {
+        self.mod_manager.stop_all()
+    }
 
-            zombie_mods.push(zm);
+    /// Restores the modules saved in `state_dir`, which is then kept up to date
+    /// with every change. Returns the ids of the restored modules.
+    /// Static modules aren't saved: they can be installed again before or after the restore.
+    /// Unless they are set already, the data root becomes `state_dir/data`
+    /// and the cache directory `state_dir/cache`.
+    pub fn restore(&self, state_dir: &str) -> Result<Vec<DynamodId>> {
+        let state_dir = PathBuf::from(state_dir);
+        if self.mod_manager.get_data_root().is_none() {
+            self.mod_manager.set_data_root(Some(state_dir.join("data")));
+        }
+        if self.mod_manager.get_cache_dir().is_none() {
+            self.mod_manager
+                .set_cache_dir(Some(state_dir.join("cache")));
         }
+        let restored = match ContainerState::load(&state_dir)? {
+            Some(state) => self.mod_manager.restore(state)?,
+            None => Vec::new(),
+        };
+        self.mod_manager.set_state_dir(Some(state_dir));
+
+        Ok(restored)
+    }
+
+    /// Opt-in hot reload: watches the files of installed modules, and reloads a module
+    /// when its file changes. Files in the deploy directory are left to `deploy`.
+    pub fn watch(&self, interval: Duration) -> Result<()> {
+        let watcher = Watcher::spawn(Arc::downgrade(&self.mod_manager), interval)?;
+        *self.watcher.lock() = Some(watcher);
+        Ok(())
+    }
+
+    pub fn unwatch(&self) {
+        self.watcher.lock().take();
+    }
+
+    /// Deploys the libraries and module archives found in `deploy_dir`: one dropped in is
+    /// installed and started, replacing it updates the module and deleting it uninstalls it.
+    pub fn deploy(&self, deploy_dir: &str, interval: Duration) -> Result<()> {
+        let deployer = Deployer::spawn(
+            Arc::downgrade(&self.mod_manager),
+            PathBuf::from(deploy_dir),
+            interval,
+        )?;
+        let mut current = self.deployer.lock();
+        self.mod_manager
+            .set_deploy_dir(Some(deployer.deploy_dir().to_owned()));
+        *current = Some(deployer);
         Ok(())
     }
 
-    pub fn start(&self, idx: DynamodId) -> Result<()> {
-        let mut mods_ptr = self.modules.lock();
-        let md = mods_ptr.get_mut(idx as usize).unwrap();
-        md.start()
+    pub fn undeploy(&self) {
+        let mut current = self.deployer.lock();
+        current.take();
+        self.mod_manager.set_deploy_dir(None);
     }
 
-    pub fn stop(&self, idx: DynamodId) -> Result<()> {
-        let mut mods_ptr = self.modules.lock();
-        let md = mods_ptr.get_mut(idx as usize).unwrap();
-        md.stop()
+    /// Declares a service provided by the host application, for module resolution.
+    pub fn declare_host_service(&self, svc_name: &str) {
+        self.mod_manager.declare_host_service(svc_name)
+    }
+
+    pub fn resolve(&self, id: DynamodId) -> Result<()> {
+        self.mod_manager.resolve(id)
+    }
+
+    pub fn get_state(&self, id: DynamodId) -> Result<DynamodState> {
+        self.mod_manager.get_state(id)
+    }
+
+    pub fn get_manifest(&self, id: DynamodId) -> Result<Manifest> {
+        self.mod_manager.get_manifest(id)
+    }
+
+    pub fn get_zombies(&self) -> Vec<ZombieInfo> {
+        self.mod_manager.get_zombies()
+    }
+
+    pub fn modules(&self) -> Vec<ModuleInfo> {
+        self.mod_manager.modules()
     }
 
     pub fn print_installed_modules(&self) {
-        let mods = self.modules.lock();
-        for md in mods.iter() {
-            println!("{}", md.path);
-        }
+        self.mod_manager.print_installed_modules();
     }
 
     pub fn register_listener<T: EventListener<ServiceEvent> + 'static>(
         &mut self,
         listener: Listener<T, ServiceEvent>,
     ) -> Result<Listener<T, ServiceEvent>> {
-        self.svc_manager.register_listener(listener.weaken());
+        self.svc_manager.register_listener(listener.weaken(), None);
+
+        Ok(listener)
+    }
+
+    pub fn register_module_listener<T: EventListener<ModuleEvent> + 'static>(
+        &self,
+        listener: Listener<T, ModuleEvent>,
+    ) -> Result<Listener<T, ModuleEvent>> {
+        self.mod_manager.register_listener(listener.weaken(), None);
 
         Ok(listener)
     }
 }
+
+// Orderly shutdown: modules are stopped by descending start level.
+impl Drop for Container {
+    fn drop(&mut self) {
+        // The saved state keeps the modules that were started, for the next launch.
+        self.mod_manager.set_state_dir(None);
+        self.unwatch();
+        self.undeploy();
+        if let Err(e) = self.stop_all() {
+            warn!("Error while stopping the container: {:?}", e);
+        }
+    }
+}
@@ -4,16 +4,36 @@ use super::*;
 pub struct Context {
     dynamod_id: DynamodId,
     svc_manager: Weak<ServiceManager>,
+    mod_manager: Weak<ModuleManager>,
+    lib: Weak<DynamodLib>,
 }
 
 impl Context {
-    pub fn new(dynamod_id: DynamodId, svc_manager: Weak<ServiceManager>) -> Context {
+    pub fn new(
+        dynamod_id: DynamodId,
+        svc_manager: Weak<ServiceManager>,
+        mod_manager: Weak<ModuleManager>,
+        lib: Weak<DynamodLib>,
+    ) -> Context {
         Context {
             dynamod_id,
             svc_manager,
+            mod_manager,
+            lib,
         }
     }
 
+    /// Id of the module this context belongs to.
+    #[inline]
+    pub fn get_id(&self) -> DynamodId {
+        self.dynamod_id
+    }
+
+    #[inline]
+    pub fn get_lib(&self) -> &Weak<DynamodLib> {
+        &self.lib
+    }
+
     pub fn use_manager_or_fail(&self) -> Result<Arc<ServiceManager>> {
         self.svc_manager
             .upgrade()
@@ -31,11 +51,71 @@ impl Context {
     ) -> Result<Listener<T, ServiceEvent>> {
         let svc_manager = self.use_manager_or_fail()?;
 
-        svc_manager.register_listener(listener.weaken());
+        // The listener only gets the events of the services the module may listen to.
+        if !svc_manager.is_allowed_any(self.dynamod_id, Permission::Listen) {
+            return Err(Error::PermissionDenied(
+                self.dynamod_id,
+                Permission::Listen,
+                "*".to_owned(),
+            ));
+        }
+
+        svc_manager.register_listener(listener.weaken(), Some(self.dynamod_id));
+
+        Ok(listener)
+    }
+
+    pub fn register_module_listener<T: EventListener<ModuleEvent> + 'static>(
+        &self,
+        listener: Listener<T, ModuleEvent>,
+    ) -> Result<Listener<T, ModuleEvent>> {
+        let mod_manager = self
+            .mod_manager
+            .upgrade()
+            .ok_or_else(|| Error::from("Socrates container is down."))?;
+
+        mod_manager.register_listener(listener.weaken(), Some(self.dynamod_id));
 
         Ok(listener)
     }
 
+    // Modules
+
+    pub fn modules(&self) -> Vec<ModuleInfo> {
+        self.mod_manager
+            .upgrade()
+            .map(|mod_manager| mod_manager.modules())
+            .unwrap_or_default()
+    }
+
+    /// A private directory for the module's data, kept across restarts and updates,
+    /// and removed when the module is uninstalled.
+    pub fn data_dir(&self) -> Result<std::path::PathBuf> {
+        let mod_manager = self
+            .mod_manager
+            .upgrade()
+            .ok_or_else(|| Error::from("Socrates container is down."))?;
+
+        mod_manager.data_dir(self.dynamod_id)
+    }
+
+    /// Reads a file from the `resources` directory of the module's archive.
+    pub fn get_resource(&self, path: &str) -> Result<Vec<u8>> {
+        let lib = self
+            .lib
+            .upgrade()
+            .ok_or_else(|| Error::from("The module's library is unloaded."))?;
+
+        lib.get_resource(path)
+    }
+
+    /// Marks the module as failed, stopping it if it is active.
+    pub fn fail(&self, reason: String) {
+        if let Some(mod_manager) = self.mod_manager.upgrade() {
+            mod_manager.fail(self.dynamod_id, reason);
+        }
+    }
+
     // Register service
 
     pub fn register_service(
@@ -47,8 +127,14 @@ impl Context {
     ) -> Result<ServiceRegistration> {
         let svc_manager = self.use_manager_or_fail()?;
 
-        let service_ref =
-            svc_manager.register_service(type_id, svc_name, svc_ranking, self.dynamod_id, svc)?;
+        let service_ref = svc_manager.register_service(
+            type_id,
+            svc_name,
+            svc_ranking,
+            self.dynamod_id,
+            self.lib.upgrade(),
+            svc,
+        )?;
 
         let srv_reg = ServiceRegistration::new(service_ref, self.shared_service_manager());
 
@@ -68,7 +154,9 @@ impl Context {
     pub fn get_service_ref(&self, svc_id: ServiceId) -> Option<ServiceRef> {
         let svc_manager = self.try_manager()?;
 
-        svc_manager.get_service_ref(svc_id)
+        svc_manager
+            .get_service_ref(svc_id)
+            .filter(|svc_ref| self.may_get(&svc_manager, svc_ref))
     }
 
     pub fn get_service(&self, svc_id: ServiceId) -> Option<Svc> {
@@ -96,7 +184,9 @@ impl Context {
 
     pub fn get_first_service_id_by_type_id(&self, svc_type_id: TypeId) -> Option<ServiceId> {
         if let Some(svc_manager) = self.try_manager() {
-            svc_manager.get_services_id_by_type_id(svc_type_id).next()
+            svc_manager
+                .get_services_id_by_type_id(svc_type_id)
+                .find(|svc_id| svc_manager.is_visible(self.dynamod_id, *svc_id))
         } else {
             None
         }
@@ -106,6 +196,7 @@ impl Context {
         if let Some(svc_manager) = self.try_manager() {
             svc_manager
                 .get_services_id_by_type_id(svc_type_id)
+                .filter(|svc_id| svc_manager.is_visible(self.dynamod_id, *svc_id))
                 .collect()
         } else {
             Vec::new()
@@ -114,7 +205,9 @@ impl Context {
 
     pub fn get_first_service_ref_by_type_id(&self, svc_type_id: TypeId) -> Option<ServiceRef> {
         if let Some(svc_manager) = self.try_manager() {
-            svc_manager.get_services_ref_by_type_id(svc_type_id).next()
+            svc_manager
+                .get_services_ref_by_type_id(svc_type_id)
+                .find(|svc_ref| self.may_get(&svc_manager, svc_ref))
         } else {
             None
         }
@@ -124,6 +217,7 @@ impl Context {
         if let Some(svc_manager) = self.try_manager() {
             svc_manager
                 .get_services_ref_by_type_id(svc_type_id)
+                .filter(|svc_ref| self.may_get(&svc_manager, svc_ref))
                 .collect()
         } else {
             Vec::new()
@@ -159,32 +253,45 @@ impl Context {
     }
 
     // Get by name
+    // The `get_first_*` lookups start the lazy modules providing the name if nothing is found.
 
     pub fn get_first_service_id_by_name(&self, svc_name: &str) -> Option<ServiceId> {
         let svc_manager = self.try_manager()?;
+        if !self.may_get_name(&svc_manager, svc_name) {
+            return None;
+        }
 
-        svc_manager.get_services_id_by_name(svc_name).next()
+        self.or_activate(svc_name, || {
+            svc_manager.get_services_id_by_name(svc_name).next()
+        })
     }
 
     pub fn get_all_services_id_by_name(&self, svc_name: &str) -> Vec<ServiceId> {
         self.try_manager()
             .into_iter()
+            .filter(|svc_manager| self.may_get_name(svc_manager, svc_name))
             .flat_map(|svc_manager| svc_manager.get_services_id_by_name(svc_name))
             .collect()
     }
 
     pub fn get_first_service_ref_by_name(&self, svc_name: &str) -> Option<ServiceRef> {
         let svc_manager = self.try_manager()?;
+        if !self.may_get_name(&svc_manager, svc_name) {
+            return None;
+        }
 
-        let s = svc_manager.get_services_ref_by_name(&svc_name).next();
-
-        s
+        self.or_activate(svc_name, || {
+            svc_manager.get_services_ref_by_name(svc_name).next()
+        })
     }
 
     pub fn get_all_services_ref_by_name(&self, svc_name: &str) -> Vec<ServiceRef> {
         let mut s: Vec<ServiceRef> = Vec::new();
 
         for svc_manager in self.try_manager() {
+            if !self.may_get_name(&svc_manager, svc_name) {
+                continue;
+            }
             for x in svc_manager.get_services_ref_by_name(svc_name) {
                 s.push(x);
             }
@@ -195,13 +302,16 @@ impl Context {
 
     pub fn get_first_service_by_name(&self, svc_name: &str) -> Option<Svc> {
         let svc_manager = self.try_manager()?;
+        if !self.may_get_name(&svc_manager, svc_name) {
+            return None;
+        }
 
-        let s = svc_manager
-            .get_services_by_name(svc_name, self.dynamod_id)
-            .map(|x| Svc::new(x.1, x.0, self.dynamod_id, self.shared_service_manager()))
-            .next();
-
-        s
+        self.or_activate(svc_name, || {
+            svc_manager
+                .get_services_by_name(svc_name, self.dynamod_id)
+                .map(|x| Svc::new(x.1, x.0, self.dynamod_id, self.shared_service_manager()))
+                .next()
+        })
     }
 
     pub fn get_all_services_by_name(&self, svc_name: &str) -> Vec<Svc> {
@@ -231,6 +341,7 @@ impl Context {
             .and_then(|svc| Svc::cast::<T>(svc).ok())
     }
 
+    /// Like all lookups by type, it doesn't start lazy modules: see `Activation::Lazy`.
     pub fn get_first_service_typed<T: Service + ?Sized>(&self) -> Option<Svc<T>> {
         let svc_type_id = service_type_id::<T>();
         self.get_first_service_by_type_id_typed(svc_type_id)
@@ -345,6 +456,29 @@ impl Context {
         }
     }
 
+    // When nothing is found, starts the lazy modules providing `svc_name` and looks up again.
+    fn or_activate<T>(&self, svc_name: &str, lookup: impl Fn() -> Option<T>) -> Option<T> {
+        lookup().or_else(|| {
+            let mod_manager = self.mod_manager.upgrade()?;
+            if mod_manager.activate_lazy(svc_name) {
+                lookup()
+            } else {
+                None
+            }
+        })
+    }
+
+    // Services the module may not get are hidden from its queries.
+    #[inline]
+    fn may_get(&self, svc_manager: &ServiceManager, svc_ref: &ServiceRef) -> bool {
+        self.may_get_name(svc_manager, &svc_ref.name)
+    }
+
+    #[inline]
+    fn may_get_name(&self, svc_manager: &ServiceManager, svc_name: &str) -> bool {
+        svc_manager.is_allowed(self.dynamod_id, Permission::Get, svc_name)
+    }
+
     #[inline]
     fn shared_service_manager(&self) -> Weak<ServiceManager> {
         Weak::clone(&self.svc_manager)
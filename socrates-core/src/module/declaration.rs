@@ -0,0 +1,169 @@
+use super::*;
+
+/// Symbol under which a module exports its `ModuleDecl`, see `declare_module!`.
+pub const MODULE_SYMBOL: &[u8] = b"SOCRATES_MODULE";
+
+/// Bumped whenever the layout of `ModuleDecl` changes.
+pub const MODULE_DECL_VERSION: u32 = 3;
+
+/// The entry point of a library, it declares one or more modules.
+///
+/// The version and the ABI fingerprint come first and have a C layout: they are checked before
+/// the rest of the declaration is trusted.
+#[repr(C)]
+pub struct ModuleDecl {
+    pub decl_version: u32,
+    pub abi: AbiFingerprint,
+    pub entries: FfiSlice<ModuleEntry>,
+}
+
+pub struct ModuleEntry {
+    pub manifest: FfiStr, // JSON, empty if the module has no manifest
+    pub vtable: ModuleVTable,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ModuleVTable {
+    pub activate: ActivateFn,
+    pub deactivate: DeactivateFn,
+    pub init_log: InitLogFn,
+}
+
+impl ModuleDecl {
+    /// Validates the declaration exported by `lib` and copies what the container needs out of it,
+    /// for each module it declares. No code from the module is run.
+    pub fn load(lib: &libloading::Library, path: &str) -> Result<Vec<(Manifest, ModuleVTable)>> {
+        let decl: libloading::Symbol<*const ModuleDecl> = unsafe { lib.get(MODULE_SYMBOL) }
+            .map_err(|_| Error::IncompatibleDeclaration {
+                expected: MODULE_DECL_VERSION,
+                found: None,
+            })?;
+        let decl: &ModuleDecl = unsafe { &**decl };
+
+        if decl.decl_version != MODULE_DECL_VERSION {
+            return Err(Error::IncompatibleDeclaration {
+                expected: MODULE_DECL_VERSION,
+                found: Some(decl.decl_version),
+            });
+        }
+
+        Abi::check(&decl.abi)?;
+
+        let entries = unsafe { decl.entries.as_slice() };
+        if entries.is_empty() {
+            return Err(Error::InvalidManifest(format!(
+                "{} doesn't declare any module",
+                path
+            )));
+        }
+
+        let mut modules = Vec::with_capacity(entries.len());
+        for (index, entry) in entries.iter().enumerate() {
+            let json = unsafe { entry.manifest.as_str() }
+                .map_err(|e| Error::InvalidManifest(e.to_string()))?;
+            let manifest = if !json.is_empty() {
+                Manifest::from_json(json)?
+            } else if entries.len() == 1 {
+                Manifest::from_path(path)
+            } else {
+                let mut manifest = Manifest::from_path(path);
+                manifest.name = format!("{}#{}", manifest.name, index);
+                manifest
+            };
+            modules.push((manifest, entry.vtable));
+        }
+
+        let mut names: Vec<&str> = modules.iter().map(|(m, _)| m.name.as_str()).collect();
+        names.sort_unstable();
+        if let Some(name) = names.windows(2).find(|w| w[0] == w[1]).map(|w| w[0]) {
+            return Err(Error::InvalidManifest(format!(
+                "{} declares module {} twice",
+                path, name
+            )));
+        }
+
+        Ok(modules)
+    }
+}
+
+/// Hands the host's logger to the module's own copy of the `log` crate.
+pub type InitLogFn = fn(&'static dyn log::Log, log::LevelFilter);
+
+/// Fails harmlessly when the module already has a logger, e.g. when it is linked into the host.
+pub fn init_log(logger: &'static dyn log::Log, level: log::LevelFilter) {
+    let _ = log::set_logger(logger);
+    log::set_max_level(level);
+}
+
+/// Declares the entry point of a module.
+///
+/// ```ignore
+/// socrates::declare_module! {
+///     manifest: include_str!("../manifest.json"), // optional
+///     activate: activate,
+///     deactivate: deactivate, // optional, defaults to dropping the activator
+/// }
+/// ```
+///
+/// A library can declare several modules, each in braces. They are installed together
+/// and share the library, but are otherwise independent:
+///
+/// ```ignore
+/// socrates::declare_module! {
+///     { manifest: include_str!("../greeter.json"), activate: activate_greeter },
+///     { manifest: include_str!("../shouter.json"), activate: activate_shouter },
+/// }
+/// ```
+#[macro_export]
+macro_rules! declare_module {
+    (@or $value:expr ; $default:expr) => {
+        $value
+    };
+    (@or ; $default:expr) => {
+        $default
+    };
+    ($({
+        $(manifest: $manifest:expr,)?
+        activate: $activate:path
+        $(, deactivate: $deactivate:path)?
+        $(,)?
+    }),+ $(,)?) => {
+        #[no_mangle]
+        pub static SOCRATES_MODULE: $crate::module::ModuleDecl = $crate::module::ModuleDecl {
+            decl_version: $crate::module::MODULE_DECL_VERSION,
+            abi: $crate::module::AbiFingerprint::CURRENT,
+            entries: {
+                const ENTRIES: &[$crate::module::ModuleEntry] = &[$(
+                    $crate::module::ModuleEntry {
+                        manifest: $crate::module::FfiStr::new(
+                            $crate::declare_module!(@or $($manifest)? ; "")
+                        ),
+                        vtable: $crate::module::ModuleVTable {
+                            activate: $activate,
+                            deactivate: $crate::declare_module!(
+                                @or $($deactivate)? ; $crate::module::default_deactivate
+                            ),
+                            init_log: $crate::module::init_log,
+                        },
+                    }
+                ),+];
+                $crate::module::FfiSlice::new(ENTRIES)
+            },
+        };
+    };
+    (
+        $(manifest: $manifest:expr,)?
+        activate: $activate:path
+        $(, deactivate: $deactivate:path)?
+        $(,)?
+    ) => {
+        $crate::declare_module! {
+            {
+                $(manifest: $manifest,)?
+                activate: $activate
+                $(, deactivate: $deactivate)?
+            }
+        }
+    };
+}
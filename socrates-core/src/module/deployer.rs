@@ -0,0 +1,185 @@
+use super::poller::{module_files, Pending, Poller};
+use super::*;
+
+use hashbrown::{HashMap, HashSet};
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Polls a deploy directory: a library or module archive dropped in is installed and started,
+/// replacing it updates the module and deleting it uninstalls the module.
+///
+/// Like the `Watcher`, a file is only picked up once it stayed the same for a full interval.
+/// Installed modules whose file is in the directory (e.g. restored ones) are adopted.
+pub struct Deployer {
+    deploy_dir: PathBuf,
+    _poller: Poller,
+}
+
+impl Deployer {
+    pub fn spawn(
+        mod_manager: Weak<ModuleManager>,
+        deploy_dir: PathBuf,
+        interval: Duration,
+    ) -> Result<Deployer> {
+        if !deploy_dir.is_dir() {
+            return Err(format!("{} is not a directory", deploy_dir.display()).into());
+        }
+        // Installed modules have absolute paths.
+        let deploy_dir = std::path::absolute(deploy_dir)?;
+
+        let mut pending = Pending::default();
+        // Deployed modules that couldn't start yet, e.g. waiting for a requirement.
+        let mut to_start: HashSet<DynamodId> = HashSet::new();
+        let poller = {
+            let deploy_dir = deploy_dir.clone();
+            Poller::spawn(
+                "socrates-deployer",
+                mod_manager,
+                interval,
+                move |mod_manager| {
+                    Deployer::scan(mod_manager, &deploy_dir, &mut pending, &mut to_start)
+                },
+            )?
+        };
+
+        Ok(Deployer {
+            deploy_dir,
+            _poller: poller,
+        })
+    }
+
+    pub fn deploy_dir(&self) -> &Path {
+        &self.deploy_dir
+    }
+
+    fn scan(
+        mod_manager: &Arc<ModuleManager>,
+        deploy_dir: &Path,
+        pending: &mut Pending,
+        to_start: &mut HashSet<DynamodId>,
+    ) {
+        let deployed = module_files(mod_manager, |path| path.parent() == Some(deploy_dir));
+
+        let files = match Deployer::list_libraries(deploy_dir) {
+            Ok(files) => files,
+            Err(e) => {
+                warn!("Couldn't list {}: {:?}", deploy_dir.display(), e);
+                return;
+            }
+        };
+
+        for (path, removed) in deployed
+            .iter()
+            .filter(|(path, _)| !files.contains_key(*path))
+        {
+            for id in removed.0.iter() {
+                info!(
+                    module_id = *id;
+                    "{} was removed, uninstalling module #{}",
+                    path.display(),
+                    id
+                );
+                to_start.remove(id);
+                if let Err(e) = mod_manager.uninstall(*id) {
+                    warn!(module_id = *id; "Couldn't uninstall module #{}: {:?}", id, e);
+                }
+            }
+        }
+
+        for (path, modified) in files.iter() {
+            let loaded = deployed.get(path);
+            if loaded.map(|(_, loaded)| *loaded) == Some(Some(*modified)) {
+                pending.forget(path);
+                continue;
+            }
+            if !pending.is_stable(path, *modified) {
+                continue;
+            }
+
+            let path_str = path.to_string_lossy();
+            match loaded {
+                // Updating one module updates all the modules of the library.
+                Some((ids, _)) => {
+                    info!(
+                        module_ids:? = ids;
+                        "{} changed, updating modules {:?}",
+                        path.display(),
+                        ids
+                    );
+                    match mod_manager.update(ids[0], &path_str) {
+                        // Modules the new library adds are started like those of a new library.
+                        Ok(()) => to_start.extend(Deployer::eager(
+                            mod_manager,
+                            mod_manager
+                                .get_module_files()
+                                .into_iter()
+                                .filter(|(id, p, _)| p.as_str() == path_str && !ids.contains(id))
+                                .map(|(id, _, _)| id),
+                        )),
+                        Err(e) => warn!(
+                            module_ids:? = ids;
+                            "Couldn't update modules {:?}: {:?}",
+                            ids,
+                            e
+                        ),
+                    }
+                }
+                None => match mod_manager.install(&path_str) {
+                    Ok(ids) => {
+                        info!(
+                            module_ids:? = ids;
+                            "{} was added, installed modules {:?}",
+                            path.display(),
+                            ids
+                        );
+                        to_start.extend(Deployer::eager(mod_manager, ids));
+                    }
+                    Err(e) => warn!("Couldn't install {}: {:?}", path.display(), e),
+                },
+            }
+        }
+
+        pending.retain(|path| files.contains_key(path));
+
+        to_start.retain(|id| match mod_manager.get_state(*id) {
+            Ok(DynamodState::Resolved) => {
+                if let Err(e) = mod_manager.start(*id) {
+                    warn!(module_id = *id; "Couldn't start module #{}: {:?}", id, e);
+                }
+                false
+            }
+            Ok(DynamodState::Installed) => true,
+            _ => false,
+        });
+    }
+
+    // Lazy modules are left to start when their services are looked up.
+    fn eager<'a, I: IntoIterator<Item = DynamodId> + 'a>(
+        mod_manager: &'a ModuleManager,
+        ids: I,
+    ) -> impl Iterator<Item = DynamodId> + 'a {
+        ids.into_iter().filter(move |id| {
+            mod_manager
+                .get_manifest(*id)
+                .is_ok_and(|manifest| !manifest.is_lazy())
+        })
+    }
+
+    fn list_libraries(deploy_dir: &Path) -> Result<HashMap<PathBuf, SystemTime>> {
+        let mut files = HashMap::new();
+        for entry in std::fs::read_dir(deploy_dir)? {
+            let path = entry?.path();
+            let is_library = path
+                .extension()
+                .is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION);
+            if !is_library && !ModuleArchive::is_archive(&path) {
+                continue;
+            }
+            if let Ok(modified) = std::fs::metadata(&path).and_then(|m| m.modified()) {
+                files.insert(path, modified);
+            }
+        }
+        Ok(files)
+    }
+}
@@ -1,70 +1,502 @@
 use super::*;
 
+use log::{debug, warn};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
 pub struct Dynamod {
     pub id: DynamodId,
     pub path: String,
+    modified: Option<SystemTime>,
+    manifest: Manifest,
+    state: DynamodState,
+    start_level: StartLevel,
+    failure: Option<String>,
+    vtable: ModuleVTable,
     activator: Option<Box<dyn Activator>>,
-    svc_manager: Weak<ServiceManager>,
-    lib: DynamodLib, // must be last to be dropped last
+    lib: Arc<DynamodLib>, // must be last to be dropped last
 }
 
 impl Dynamod {
-    pub fn new(
-        id: DynamodId,
-        svc_manager: Weak<ServiceManager>,
+    /// Loads the library at `path` with every module it declares, they share the library.
+    /// The file is verified against `trust` before anything is loaded from it, and
+    /// a module archive is extracted into `cache_dir`.
+    /// The modules get their id when they are installed.
+    pub fn load(path: &str, cache_dir: &Path, trust: &TrustPolicy) -> Result<Vec<Dynamod>> {
+        let modified = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        // What is verified is what is loaded, even if the file changes in between.
+        let bytes = std::fs::read(path)?;
+        trust.verify(Path::new(path), &bytes)?;
+
+        if !ModuleArchive::is_archive(Path::new(path)) {
+            let lib = DynamodLib::open(Path::new(path), &bytes)?;
+            let entries = ModuleDecl::load(&lib, path)?;
+            let lib = DynamodLib::new(path, Some(lib));
+            return Ok(Dynamod::from_entries(path, modified, entries, lib));
+        }
+
+        let archive = ModuleArchive::extract(Path::new(path), &bytes, cache_dir)?;
+        // From here on, dropping the library removes the extracted archive.
+        // Each archive is extracted in its own directory, so its library can be loaded in place.
+        let lib = libloading::Library::new(&archive.library).map(|lib| DynamodLib {
+            path: path.to_owned(),
+            declared: Vec::new(),
+            extracted: Some(archive.dir.clone()),
+            lib: Some(lib),
+        });
+        let lib = match lib {
+            Ok(lib) => lib,
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&archive.dir);
+                return Err(e.into());
+            }
+        };
+
+        let mut entries = ModuleDecl::load(lib.lib.as_ref().unwrap(), path)?;
+        if let Some(manifest) = archive.manifest {
+            if entries.len() != 1 {
+                return Err(Error::InvalidManifest(format!(
+                    "{} has a manifest but its library declares {} modules",
+                    path,
+                    entries.len()
+                )));
+            }
+            entries[0].0 = manifest;
+        }
+
+        Ok(Dynamod::from_entries(path, modified, entries, lib))
+    }
+
+    fn from_entries(
         path: &str,
-        lib: libloading::Library,
-    ) -> Dynamod {
+        modified: Option<SystemTime>,
+        entries: Vec<(Manifest, ModuleVTable)>,
+        mut lib: DynamodLib,
+    ) -> Vec<Dynamod> {
+        lib.declared = entries.iter().map(|(m, _)| m.name.clone()).collect();
+        let lib = Arc::new(lib);
+
+        entries
+            .into_iter()
+            .map(|(manifest, vtable)| {
+                let start_level = manifest.start_level.unwrap_or(DEFAULT_START_LEVEL);
+                Dynamod {
+                    id: 0,
+                    path: path.to_owned(),
+                    modified,
+                    manifest,
+                    state: DynamodState::Installed,
+                    start_level,
+                    failure: None,
+                    vtable,
+                    activator: None,
+                    lib: Arc::clone(&lib),
+                }
+            })
+            .collect()
+    }
+
+    /// A module linked into the host, it has no file and no library to load.
+    pub fn new_static(id: DynamodId, manifest: Manifest, vtable: ModuleVTable) -> Dynamod {
+        let start_level = manifest.start_level.unwrap_or(DEFAULT_START_LEVEL);
+        let path = format!("static:{}", manifest.name);
+        let lib = Arc::new(DynamodLib::new(&path, None));
+
         Dynamod {
             id,
-            path: path.to_owned(),
+            path,
+            modified: None,
+            manifest,
+            state: DynamodState::Installed,
+            start_level,
+            failure: None,
+            vtable,
             activator: None,
-            svc_manager,
-            lib: DynamodLib::new(id, lib),
+            lib,
         }
     }
 
-    fn activate(&self, ctx: Context) -> Result<Box<Activator>> {
-        let activate_fn: libloading::Symbol<ActivateFn> = unsafe { self.lib.lib.get(b"activate")? };
+    #[inline]
+    pub fn is_static(&self) -> bool {
+        self.lib.is_static()
+    }
+
+    /// The module's services hold on to it, so that it is unloaded only when they are all gone.
+    #[inline]
+    pub fn get_lib(&self) -> Weak<DynamodLib> {
+        Arc::downgrade(&self.lib)
+    }
+
+    /// Whether both modules were loaded from the same library.
+    #[inline]
+    pub fn shares_lib_with(&self, other: &Dynamod) -> bool {
+        Arc::ptr_eq(&self.lib, &other.lib)
+    }
+
+    /// Names of all the modules its library declares, installed or not.
+    pub fn declared(&self) -> &[String] {
+        &self.lib.declared
+    }
+
+    /// Modification time of the file when it was loaded.
+    #[inline]
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
 
+    #[inline]
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    #[inline]
+    pub fn state(&self) -> DynamodState {
+        self.state
+    }
+
+    #[inline]
+    pub fn start_level(&self) -> StartLevel {
+        self.start_level
+    }
+
+    #[inline]
+    pub fn set_start_level(&mut self, start_level: StartLevel) {
+        self.start_level = start_level;
+    }
+
+    /// Why the module last failed, cleared when it starts again.
+    #[inline]
+    pub fn failure(&self) -> Option<&str> {
+        self.failure.as_deref()
+    }
+
+    #[inline]
+    pub fn set_failure(&mut self, reason: String) {
+        self.failure = Some(reason);
+    }
+
+    fn record_failure(&mut self, e: &Error) {
+        if self.failure.is_none() {
+            self.failure = Some(match e {
+                Error::Panic(message) | Error::ModuleFailed(_, message) => message.clone(),
+                e => format!("{:?}", e),
+            });
+        }
+    }
+
+    pub fn set_state(&mut self, next: DynamodState) -> Result<()> {
+        if self.state.can_transition_to(next) {
+            self.state = next;
+            Ok(())
+        } else {
+            Err(Error::InvalidModuleState(self.id, self.state))
+        }
+    }
+
+    #[inline]
+    pub fn activate_fn(&self) -> ActivateFn {
+        self.vtable.activate
+    }
+
+    #[inline]
+    pub fn deactivate_fn(&self) -> DeactivateFn {
+        self.vtable.deactivate
+    }
+
+    #[inline]
+    pub fn init_log_fn(&self) -> InitLogFn {
+        self.vtable.init_log
+    }
+
+    // Called without holding the container lock, so that the module can use its `Context` freely.
+    pub fn activate(
+        activate_fn: ActivateFn,
+        init_log_fn: InitLogFn,
+        ctx: Context,
+    ) -> Result<Box<dyn Activator>> {
         // Somehow a panic in activate leads to a segfault after full unwinding.
-        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| activate_fn(ctx)))
-            .map_err(|_| "Panic in Activate".to_owned().into())
-            .and_then(|r| r)
+        catch_panic(|| {
+            init_log_fn(log::logger(), log::max_level());
+            activate_fn(ctx)
+        })
+        .map_err(|message| Error::Panic(format!("Panic in activate: {}", message)))
+        .and_then(|r| r)
     }
 
-    pub fn start(&mut self) -> Result<()> {
-        self.activator =
-            Some(self.activate(Context::new(self.id, Weak::clone(&self.svc_manager)))?);
+    pub fn begin_start(&mut self) -> Result<()> {
+        self.set_state(DynamodState::Starting)?;
+        self.failure = None;
         Ok(())
     }
-    pub fn stop(&mut self) -> Result<()> {
-        self.activator = None; // drop activated, we'll make a new one if we start again.
-        Ok(())
+
+    pub fn finish_start(&mut self, activated: Result<Box<dyn Activator>>) -> Result<()> {
+        match activated {
+            Ok(activator) => {
+                self.activator = Some(activator);
+                self.set_state(DynamodState::Active)
+            }
+            Err(e) => {
+                self.record_failure(&e);
+                self.set_state(DynamodState::Failed)?;
+                Err(e)
+            }
+        }
     }
 
-    pub fn zombify(self) -> Dynamod {
-        // Drop the activator and put a ZombieActivator instead
+    pub fn begin_stop(&mut self) -> Result<Option<Box<dyn Activator>>> {
+        self.set_state(DynamodState::Stopping)?;
+        Ok(self.activator.take())
+    }
 
-        let zm = Dynamod {
-            activator: None,
-            ..self
+    // Called without holding the container lock, like `activate`.
+    // With a timeout, the module stops on its own thread, which is abandoned if it hangs:
+    // it then keeps the library loaded until it returns, and hands its result to `abandoned`.
+    pub fn deactivate<F>(
+        deactivate_fn: DeactivateFn,
+        activator: Box<dyn Activator>,
+        ctx: Context,
+        timeout: Option<Duration>,
+        abandoned: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(Result<()>) + Send + 'static,
+    {
+        let id = ctx.get_id();
+        let lib = ctx.get_lib().upgrade();
+        let stop = move || {
+            let mut activator = activator;
+            // This drops the activator too, whatever the module holds in it.
+            catch_panic(|| {
+                let stopped = activator.stop(&ctx);
+                stopped.and(deactivate_fn(activator))
+            })
+            .map_err(|message| Error::Panic(format!("Panic in deactivate: {}", message)))
+            .and_then(|r| r)
+        };
+
+        let timeout = match timeout {
+            Some(timeout) => timeout,
+            None => return stop(),
         };
-        zm
+
+        // Set under its lock once we stop waiting, so that the result is never lost.
+        let is_abandoned = Arc::new(Mutex::new(false));
+        let (sender, receiver) = mpsc::channel();
+        {
+            let is_abandoned = Arc::clone(&is_abandoned);
+            thread::Builder::new()
+                .name(format!("socrates-stop-{}", id))
+                .spawn(move || {
+                    let _lib = lib;
+                    let stopped = stop();
+                    let is_abandoned = is_abandoned.lock();
+                    if *is_abandoned {
+                        drop(is_abandoned);
+                        abandoned(stopped);
+                    } else {
+                        let _ = sender.send(stopped);
+                    }
+                })?;
+        }
+
+        match receiver.recv_timeout(timeout) {
+            Ok(stopped) => stopped,
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(Error::Panic(format!(
+                "The stop thread of module #{} died",
+                id
+            ))),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let mut is_abandoned = is_abandoned.lock();
+                // It may have returned in the meantime.
+                if let Ok(stopped) = receiver.try_recv() {
+                    return stopped;
+                }
+                *is_abandoned = true;
+                warn!(
+                    module_id = id;
+                    "Module #{} didn't stop within {:?}, abandoning it",
+                    id, timeout
+                );
+                Err(Error::StopTimeout(id))
+            }
+        }
+    }
+
+    pub fn finish_stop(&mut self, deactivated: Result<()>) -> Result<()> {
+        match deactivated {
+            Ok(()) => self.set_state(DynamodState::Resolved),
+            Err(e) => {
+                self.record_failure(&e);
+                self.set_state(DynamodState::Failed)?;
+                Err(e)
+            }
+        }
+    }
+
+    pub fn zombify(&mut self) -> Result<()> {
+        self.set_state(DynamodState::Zombie)?;
+        self.activator = None;
+        Ok(())
+    }
+
+    // Releases our strong reference on the library, it is unloaded if no service uses it anymore.
+    pub fn into_zombie(self) -> ZombieDynamod {
+        ZombieDynamod {
+            id: self.id,
+            path: self.path.clone(),
+            lib: self.get_lib(),
+        }
+    }
+}
+
+/// An uninstalled (or replaced) module, whose library may still be loaded.
+pub struct ZombieDynamod {
+    pub id: DynamodId,
+    pub path: String,
+    lib: Weak<DynamodLib>,
+}
+
+impl ZombieDynamod {
+    #[inline]
+    pub fn is_loaded(&self) -> bool {
+        self.lib.strong_count() > 0
+    }
+
+    #[inline]
+    pub fn get_lib(&self) -> &Weak<DynamodLib> {
+        &self.lib
     }
 }
 
-struct DynamodLib {
-    id: DynamodId,
-    lib: libloading::Library, // must be last to be dropped last
+/// A zombie module, with the services that keep it loaded and the modules using them.
+/// Handles leaked by stopped modules keep it loaded too, they are not in `held_by`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZombieInfo {
+    pub id: DynamodId,
+    pub path: String,
+    pub services: Vec<ServiceRef>,
+    pub held_by: Vec<DynamodId>,
 }
+
+/// An installed module, with the services it registered and the ones it uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleInfo {
+    pub id: DynamodId,
+    pub path: String,
+    pub state: DynamodState,
+    pub start_level: StartLevel,
+    pub failure: Option<String>,
+    pub manifest: Manifest,
+    pub registered: Vec<ServiceRef>,
+    pub used: Vec<(ServiceRef, u32)>,
+}
+
+// Static modules have one too, without library, so that their services are accounted the same way.
+// Shared by all the modules declared in the library.
+pub struct DynamodLib {
+    path: String,
+    declared: Vec<String>,            // names of all the modules it declares
+    extracted: Option<PathBuf>,       // where its archive was extracted, if it came from one
+    lib: Option<libloading::Library>, // must be last to be dropped last
+}
+
+static PRIVATE_DIR_COUNT: AtomicUsize = AtomicUsize::new(0);
+
 impl DynamodLib {
-    pub fn new(id: DynamodId, lib: libloading::Library) -> DynamodLib {
-        DynamodLib { id, lib }
+    pub fn new(path: &str, lib: Option<libloading::Library>) -> DynamodLib {
+        DynamodLib {
+            path: path.to_owned(),
+            declared: Vec::new(),
+            extracted: None,
+            lib,
+        }
+    }
+
+    /// A file from the resources of the module's archive.
+    pub fn get_resource(&self, resource: &str) -> Result<Vec<u8>> {
+        let extracted = self
+            .extracted
+            .as_ref()
+            .ok_or_else(|| format!("{} is not a module archive, it has no resources", self.path))?;
+
+        Ok(std::fs::read(ModuleArchive::resource_path(
+            extracted, resource,
+        )?)?)
+    }
+
+    #[inline]
+    pub fn is_static(&self) -> bool {
+        self.lib.is_none()
+    }
+
+    /// Creates a new directory in `parent` that only the current user can access.
+    /// A directory that already exists is never reused: someone else may have prepared it.
+    pub(crate) fn create_private_dir(parent: &Path, prefix: &str) -> Result<PathBuf> {
+        std::fs::create_dir_all(parent)?;
+
+        let mut builder = std::fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        for _ in 0..100 {
+            let dir = parent.join(format!(
+                "{}-{}-{}",
+                prefix,
+                std::process::id(),
+                PRIVATE_DIR_COUNT.fetch_add(1, Ordering::SeqCst)
+            ));
+            match builder.create(&dir) {
+                Ok(()) => return Ok(dir),
+                Err(ref e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(format!(
+            "Couldn't create a private directory in {}",
+            parent.display()
+        )
+        .into())
+    }
+
+    // The dynamic loader hands back the already loaded library when asked for the same path
+    // twice, so we load a private copy: the file can then be replaced and loaded again.
+    // The copy is written to a fresh private directory, so nobody can swap it before it's loaded.
+    fn open(path: &Path, bytes: &[u8]) -> Result<libloading::Library> {
+        let shadow_dir = DynamodLib::create_private_dir(&std::env::temp_dir(), "socrates")?;
+        let mut shadow_path = shadow_dir.join("module");
+        if let Some(ext) = path.extension() {
+            shadow_path.set_extension(ext);
+        }
+
+        let lib = DynamodLib::write_new(&shadow_path, bytes)
+            .and_then(|()| Ok(libloading::Library::new(&shadow_path)?));
+        // Once loaded, the copy is not needed anymore (this fails harmlessly where it's in use).
+        let _ = std::fs::remove_file(&shadow_path);
+        let _ = std::fs::remove_dir(&shadow_dir);
+
+        lib
+    }
+
+    fn write_new(path: &Path, bytes: &[u8]) -> Result<()> {
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        std::io::Write::write_all(&mut file, bytes)?;
+        Ok(())
     }
 }
 impl Drop for DynamodLib {
     fn drop(&mut self) {
-        println!("Dropping dynamod #{}", self.id);
+        debug!(path = self.path.as_str(); "Dropping dynamod library {}", self.path);
+        if let Some(ref extracted) = self.extracted {
+            if let Err(e) = std::fs::remove_dir_all(extracted) {
+                warn!("Couldn't remove {}: {:?}", extracted.display(), e);
+            }
+        }
     }
 }
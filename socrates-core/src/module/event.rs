@@ -0,0 +1,36 @@
+use super::*;
+
+pub trait ModuleEventListener: EventListener<ModuleEvent> {}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ModuleEvent {
+    ModuleInstalled(DynamodId),
+    ModuleResolved(DynamodId),
+    /// The module can't be started, these requirements are missing. Fired when a module is
+    /// installed (or updated) unresolved, and when a resolved module loses a requirement.
+    ModuleUnresolved(DynamodId, Vec<String>),
+    ModuleStarting(DynamodId),
+    ModuleStarted(DynamodId),
+    ModuleStopping(DynamodId),
+    ModuleStopped(DynamodId),
+    ModuleFailed(DynamodId),
+    ModuleUpdated(DynamodId),
+    ModuleUninstalled(DynamodId),
+}
+
+impl ModuleEvent {
+    pub fn get_module_id(&self) -> DynamodId {
+        match self {
+            ModuleEvent::ModuleInstalled(id)
+            | ModuleEvent::ModuleResolved(id)
+            | ModuleEvent::ModuleUnresolved(id, _)
+            | ModuleEvent::ModuleStarting(id)
+            | ModuleEvent::ModuleStarted(id)
+            | ModuleEvent::ModuleStopping(id)
+            | ModuleEvent::ModuleStopped(id)
+            | ModuleEvent::ModuleFailed(id)
+            | ModuleEvent::ModuleUpdated(id)
+            | ModuleEvent::ModuleUninstalled(id) => *id,
+        }
+    }
+}
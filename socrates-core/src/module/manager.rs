@@ -0,0 +1,1097 @@
+use super::*;
+
+use hashbrown::HashSet;
+use log::{error, info, warn};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+pub type ModuleListeners = Listeners<ModuleEvent>;
+
+/// Start level of the modules whose manifest doesn't specify one.
+pub const DEFAULT_START_LEVEL: StartLevel = 1;
+
+/// Static modules get their ids from here on. They aren't saved, so their ids never collide
+/// with the saved ones and stay the same from one launch to the next, as long as the host
+/// installs them in the same order.
+pub const FIRST_STATIC_ID: DynamodId = 1 << 31;
+
+pub struct ModuleManager {
+    this: Weak<ModuleManager>,
+    svc_manager: Weak<ServiceManager>,
+    curr_id: Mutex<DynamodId>,
+    curr_static_id: Mutex<DynamodId>,
+    modules: Mutex<BTreeMap<DynamodId, Dynamod>>,
+    zombie_modules: Mutex<Vec<ZombieDynamod>>,
+    start_level: Mutex<StartLevel>,
+    state_dir: Mutex<Option<PathBuf>>,
+    stop_timeout: Mutex<Option<Duration>>,
+    data_root: Mutex<Option<PathBuf>>,
+    cache_dir: Mutex<Option<PathBuf>>,
+    deploy_dir: Mutex<Option<PathBuf>>,
+    trust_policy: RwLock<TrustPolicy>,
+    permission_policy: RwLock<PermissionPolicy>,
+    host_services: RwLock<HashSet<String>>,
+    pub listeners: RwLock<ModuleListeners>,
+}
+
+impl ModuleManager {
+    pub fn new(svc_manager: Weak<ServiceManager>) -> Arc<ModuleManager> {
+        Arc::new_cyclic(|this| ModuleManager {
+            this: Weak::clone(this),
+            svc_manager,
+            curr_id: Default::default(),
+            curr_static_id: Mutex::new(FIRST_STATIC_ID - 1),
+            modules: Default::default(),
+            zombie_modules: Default::default(),
+            start_level: Default::default(),
+            state_dir: Default::default(),
+            stop_timeout: Default::default(),
+            data_root: Default::default(),
+            cache_dir: Default::default(),
+            deploy_dir: Default::default(),
+            trust_policy: Default::default(),
+            permission_policy: Default::default(),
+            host_services: Default::default(),
+            listeners: Default::default(),
+        })
+    }
+
+    fn make_context(&self, id: DynamodId, lib: Weak<DynamodLib>) -> Context {
+        Context::new(
+            id,
+            Weak::clone(&self.svc_manager),
+            Weak::clone(&self.this),
+            lib,
+        )
+    }
+
+    pub fn register_listener(&self, listener: WeakListener<ModuleEvent>, owner: Option<DynamodId>) {
+        let mut listeners = self.listeners.write();
+
+        listeners.insert_listener(listener, owner);
+    }
+
+    // Whatever a module didn't clean up itself when it stopped (or failed to start).
+    fn release_module(&self, id: DynamodId) {
+        if let Some(svc_manager) = self.svc_manager.upgrade() {
+            svc_manager.release_module(id);
+        }
+
+        let listeners = self.listeners.write().remove_owned_by(id);
+        if listeners > 0 {
+            warn!(
+                module_id = id;
+                "Module #{} leaked {} module listener(s), removing them",
+                id, listeners
+            );
+        }
+    }
+
+    fn fire_event(&self, event: &ModuleEvent) {
+        let listeners = self.listeners.read().clone();
+        let (dirty, panics) = listeners.fire_event(event);
+        if dirty {
+            self.listeners.write().clean_up();
+        }
+        self.report_panics(panics);
+    }
+
+    /// Fails the modules whose listeners panicked, panics in host listeners are only logged.
+    pub fn report_panics(&self, panics: ListenerPanics) {
+        for (owner, message) in panics {
+            match owner {
+                Some(id) => self.fail(id, format!("Panic in a listener: {}", message)),
+                None => error!("A host listener panicked: {}", message),
+            }
+        }
+    }
+
+    /// Records why the module failed and stops it into the `Failed` state if it is active.
+    /// A module that is starting fails once its activate function returns, one that is
+    /// stopping once its stop returns.
+    pub fn fail(&self, id: DynamodId, reason: String) {
+        error!(module_id = id; "Module #{} failed: {}", id, reason);
+
+        let state = {
+            let mut mods = self.modules.lock();
+            match mods.get_mut(&id) {
+                Some(md) => {
+                    md.set_failure(reason.clone());
+                    md.state()
+                }
+                None => return,
+            }
+        };
+
+        if state.is_active() {
+            // The error is the failure we're reporting.
+            let _ = self.stop_with(id, Some(reason));
+        }
+    }
+
+    fn get_mut(mods: &mut BTreeMap<DynamodId, Dynamod>, id: DynamodId) -> Result<&mut Dynamod> {
+        mods.get_mut(&id).ok_or(Error::ModuleNotFound(id))
+    }
+
+    // Ids are never reused, even after an uninstall.
+    fn next_id(&self) -> DynamodId {
+        let mut curr_id = self.curr_id.lock();
+        *curr_id += 1;
+        *curr_id
+    }
+
+    fn next_static_id(&self) -> DynamodId {
+        let mut curr_static_id = self.curr_static_id.lock();
+        *curr_static_id += 1;
+        *curr_static_id
+    }
+
+    // Lifecycle
+
+    /// Installs every module declared by the library at `path`, returns their ids.
+    pub fn install(&self, path: &str) -> Result<Vec<DynamodId>> {
+        let path = ModuleManager::absolute_path(path)?;
+        let ids: Vec<DynamodId> = self
+            .load(&path)?
+            .into_iter()
+            .map(|dyn_mod| self.insert_new(dyn_mod))
+            .collect();
+        self.resolve_installed(&ids);
+        self.persist();
+
+        Ok(ids)
+    }
+
+    fn insert_new(&self, mut dyn_mod: Dynamod) -> DynamodId {
+        let id = self.next_id();
+        dyn_mod.id = id;
+        self.insert(dyn_mod);
+
+        id
+    }
+
+    /// Installs a module linked into the host, it goes through the same lifecycle as the others.
+    pub fn install_static(&self, manifest: Manifest, vtable: ModuleVTable) -> DynamodId {
+        let id = self.next_static_id();
+        self.insert(Dynamod::new_static(id, manifest, vtable));
+        self.resolve_installed(&[id]);
+
+        id
+    }
+
+    fn insert(&self, dyn_mod: Dynamod) {
+        let id = dyn_mod.id;
+        self.modules.lock().insert(id, dyn_mod);
+
+        self.fire_event(&ModuleEvent::ModuleInstalled(id));
+    }
+
+    /// Modules using the services of `id`, they would keep references into a zombie
+    /// if it was uninstalled.
+    pub fn get_dependents(&self, id: DynamodId) -> Result<Vec<DynamodId>> {
+        self.get_state(id)?;
+
+        Ok(self
+            .svc_manager
+            .upgrade()
+            .map(|svc_manager| svc_manager.get_users_of_services_owned_by(id))
+            .unwrap_or_default())
+    }
+
+    /// Uninstalls the module even if others use its services, returns these dependents.
+    pub fn uninstall(&self, id: DynamodId) -> Result<Vec<DynamodId>> {
+        let dependents = self.get_dependents(id)?;
+        if !dependents.is_empty() {
+            warn!(
+                module_id = id;
+                "Uninstalling module #{} while modules {:?} use its services",
+                id, dependents
+            );
+        }
+
+        let state = self.get_state(id)?;
+        if state.is_active() {
+            self.stop(id)?;
+        }
+
+        {
+            let mut mods = self.modules.lock();
+            ModuleManager::get_mut(&mut mods, id)?.zombify()?;
+            let zm = mods.remove(&id).ok_or(Error::ModuleNotFound(id))?;
+
+            self.bury(zm);
+        }
+
+        self.remove_data_dir(id);
+
+        self.fire_event(&ModuleEvent::ModuleUninstalled(id));
+        self.resolve_all();
+        self.persist();
+
+        Ok(dependents)
+    }
+
+    /// Refuses to uninstall a module whose services are in use.
+    pub fn uninstall_if_unused(&self, id: DynamodId) -> Result<()> {
+        let dependents = self.get_dependents(id)?;
+        if !dependents.is_empty() {
+            return Err(Error::ModuleInUse(id, dependents));
+        }
+
+        self.uninstall(id).map(|_| ())
+    }
+
+    /// Restarts the active modules that still use services of stopped or uninstalled modules,
+    /// so that they bind to live ones and the zombies can be unloaded. Returns the restarted modules.
+    pub fn refresh(self: &Arc<Self>) -> Result<Vec<DynamodId>> {
+        let zombie_users = self
+            .svc_manager
+            .upgrade()
+            .map(|svc_manager| svc_manager.get_zombie_users())
+            .unwrap_or_default();
+
+        let to_refresh =
+            self.ids_by_start_level(|md| md.state().is_active() && zombie_users.contains(&md.id));
+
+        let stopped = self.stop_ordered(to_refresh.clone());
+        let started = self.start_ordered(to_refresh.clone());
+
+        stopped.and(started).map(|()| to_refresh)
+    }
+
+    /// Loads the module's file again, keeping its id.
+    pub fn reload(self: &Arc<Self>, id: DynamodId) -> Result<()> {
+        let path = {
+            let mods = self.modules.lock();
+            let md = mods.get(&id).ok_or(Error::ModuleNotFound(id))?;
+            if md.is_static() {
+                return Err(format!("Module #{} is static, it has no file to reload", id).into());
+            }
+            md.path.clone()
+        };
+
+        self.update(id, &path)
+    }
+
+    /// Replaces the module's library with the one at `path`, keeping its id. The modules
+    /// sharing the library are updated as well, matched by name in the new library, and the
+    /// modules only the new library declares are installed: the ones uninstalled from the old
+    /// library stay uninstalled.
+    /// The old library becomes a zombie and the active modules are restarted.
+    pub fn update(self: &Arc<Self>, id: DynamodId, path: &str) -> Result<()> {
+        let path = &ModuleManager::absolute_path(path)?;
+        let (siblings, declared): (Vec<(DynamodId, String)>, Vec<String>) = {
+            let mods = self.modules.lock();
+            let md = mods.get(&id).ok_or(Error::ModuleNotFound(id))?;
+            let siblings = mods
+                .values()
+                .filter(|other| other.shares_lib_with(md))
+                .map(|other| (other.id, other.manifest().name.clone()))
+                .collect();
+            (siblings, md.declared().to_vec())
+        };
+
+        // Load first: if the new library is refused, the modules keep running the old one.
+        let mut added = self.load(path)?;
+        let mut new_mods = Vec::with_capacity(siblings.len());
+        for (sibling_id, name) in siblings.iter() {
+            // A library declaring a single module may rename it.
+            let index = if siblings.len() == 1 && added.len() == 1 {
+                Some(0)
+            } else {
+                added.iter().position(|md| md.manifest().name == *name)
+            };
+            let mut new_mod = match index {
+                Some(index) => added.remove(index),
+                None => {
+                    return Err(Error::InvalidManifest(format!(
+                        "{} doesn't declare module {}",
+                        path, name
+                    )))
+                }
+            };
+            new_mod.id = *sibling_id;
+            new_mods.push(new_mod);
+        }
+        // Modules the old library declared too were uninstalled on purpose.
+        added.retain(|md| !declared.contains(&md.manifest().name));
+
+        let was_active = self.ids_by_start_level(|md| {
+            md.state().is_active() && new_mods.iter().any(|n| n.id == md.id)
+        });
+        if let Err(e) = self.stop_ordered(was_active.clone()) {
+            // The modules keep running the old library.
+            self.restart_stopped(&was_active);
+            return Err(e);
+        }
+
+        let updated: Result<Vec<DynamodId>> = {
+            let mut mods = self.modules.lock();
+            // Every sibling is checked first: they are all swapped or none is, e.g. when one
+            // is still stopping.
+            let swappable = new_mods.iter().try_for_each(|new_mod| {
+                let md = mods
+                    .get(&new_mod.id)
+                    .ok_or(Error::ModuleNotFound(new_mod.id))?;
+                if md.state().can_transition_to(DynamodState::Zombie) {
+                    Ok(())
+                } else {
+                    Err(Error::InvalidModuleState(md.id, md.state()))
+                }
+            });
+            swappable.and_then(|()| {
+                new_mods
+                    .into_iter()
+                    .map(|mut new_mod| {
+                        let id = new_mod.id;
+                        let md = ModuleManager::get_mut(&mut mods, id)?;
+                        md.zombify()?;
+                        new_mod.set_start_level(md.start_level());
+                        let zm = std::mem::replace(md, new_mod);
+
+                        self.bury(zm);
+                        Ok(id)
+                    })
+                    .collect()
+            })
+        };
+        let updated = match updated {
+            Ok(updated) => updated,
+            Err(e) => {
+                self.restart_stopped(&was_active);
+                return Err(e);
+            }
+        };
+        for id in updated {
+            self.fire_event(&ModuleEvent::ModuleUpdated(id));
+        }
+
+        let mut installed: Vec<DynamodId> = siblings.iter().map(|(id, _)| *id).collect();
+        for new_mod in added {
+            let id = self.insert_new(new_mod);
+            info!(module_id = id; "Installed module #{} added to {}", id, path);
+            installed.push(id);
+        }
+
+        self.resolve_installed(&installed);
+        self.persist();
+
+        self.start_ordered(was_active)
+    }
+
+    // Starts the modules among `ids` that are stopped, when an operation that stopped them is abandoned.
+    fn restart_stopped(self: &Arc<Self>, ids: &[DynamodId]) {
+        let stopped = self
+            .ids_by_start_level(|md| ids.contains(&md.id) && md.state() == DynamodState::Resolved);
+        // Failures are logged.
+        let _ = self.start_ordered(stopped);
+    }
+
+    fn bury(&self, zm: Dynamod) {
+        // Dead listeners are only weak references, but dropping them needs the library's code.
+        if let Some(svc_manager) = self.svc_manager.upgrade() {
+            svc_manager.listeners.write().clean_up();
+        }
+        self.listeners.write().clean_up();
+
+        let mut zombie_mods = self.zombie_modules.lock();
+        zombie_mods.retain(ZombieDynamod::is_loaded);
+        zombie_mods.push(zm.into_zombie());
+    }
+
+    /// Zombie modules whose library is still loaded, because some of their services are still used
+    /// or another module of the library is still installed.
+    pub fn get_zombies(&self) -> Vec<ZombieInfo> {
+        let mut zombie_mods = self.zombie_modules.lock();
+        zombie_mods.retain(ZombieDynamod::is_loaded);
+
+        let svc_manager = self.svc_manager.upgrade();
+        zombie_mods
+            .iter()
+            .map(|zm| {
+                let services = svc_manager
+                    .as_ref()
+                    .map(|svc_manager| svc_manager.get_zombie_services(zm.id, zm.get_lib()))
+                    .unwrap_or_default();
+
+                let mut held_by: Vec<DynamodId> = services
+                    .iter()
+                    .flat_map(|(_, users)| users.iter().cloned())
+                    .collect();
+                held_by.sort();
+                held_by.dedup();
+
+                ZombieInfo {
+                    id: zm.id,
+                    path: zm.path.clone(),
+                    services: services.into_iter().map(|(svc_ref, _)| svc_ref).collect(),
+                    held_by,
+                }
+            })
+            .collect()
+    }
+
+    // The activator is called without holding the lock on modules.
+    pub fn start(self: &Arc<Self>, id: DynamodId) -> Result<()> {
+        self.resolve(id)?;
+
+        let (activate_fn, deactivate_fn, init_log_fn, lib, permissions) = {
+            let mut mods = self.modules.lock();
+            let md = ModuleManager::get_mut(&mut mods, id)?;
+            md.begin_start()?;
+            (
+                md.activate_fn(),
+                md.deactivate_fn(),
+                md.init_log_fn(),
+                md.get_lib(),
+                self.permission_policy
+                    .read()
+                    .permissions_of(&md.path, md.manifest()),
+            )
+        };
+
+        // Kept once the module stops: the code of a stopped or zombie module may still run.
+        if let Some(svc_manager) = self.svc_manager.upgrade() {
+            svc_manager.set_permissions(id, permissions);
+        }
+
+        self.fire_event(&ModuleEvent::ModuleStarting(id));
+
+        let ctx = self.make_context(id, Weak::clone(&lib));
+        let activated = Dynamod::activate(activate_fn, init_log_fn, ctx);
+
+        // The module may have failed while starting, e.g. in one of its listeners.
+        let failure = self
+            .modules
+            .lock()
+            .get(&id)
+            .and_then(|md| md.failure().map(str::to_owned));
+        let activated = match (activated, failure) {
+            (Ok(activator), Some(reason)) => {
+                let ctx = self.make_context(id, lib);
+                let timeout = self.get_stop_timeout();
+                let this = Weak::clone(&self.this);
+                let failed = reason.clone();
+                let deactivated =
+                    Dynamod::deactivate(deactivate_fn, activator, ctx, timeout, move |_| {
+                        if let Some(mod_manager) = this.upgrade() {
+                            let _ =
+                                mod_manager.finish_start(id, Err(Error::ModuleFailed(id, failed)));
+                        }
+                    });
+                if let Err(Error::StopTimeout(_)) = deactivated {
+                    // Abandoned, it is still starting until its deactivation returns.
+                    return Err(Error::ModuleFailed(id, reason));
+                }
+                Err(Error::ModuleFailed(id, reason))
+            }
+            (activated, _) => activated,
+        };
+
+        self.finish_start(id, activated)
+    }
+
+    fn finish_start(&self, id: DynamodId, activated: Result<Box<dyn Activator>>) -> Result<()> {
+        let result = {
+            let mut mods = self.modules.lock();
+            ModuleManager::get_mut(&mut mods, id)?.finish_start(activated)
+        };
+
+        match result {
+            Ok(()) => self.fire_event(&ModuleEvent::ModuleStarted(id)),
+            Err(_) => {
+                self.release_module(id);
+                self.fire_event(&ModuleEvent::ModuleFailed(id))
+            }
+        }
+        self.persist();
+
+        result
+    }
+
+    pub fn stop(&self, id: DynamodId) -> Result<()> {
+        self.stop_with(id, None)
+    }
+
+    // The activator is deactivated without holding the lock on modules.
+    fn stop_with(&self, id: DynamodId, failure: Option<String>) -> Result<()> {
+        let (activator, deactivate_fn, lib) = {
+            let mut mods = self.modules.lock();
+            let md = ModuleManager::get_mut(&mut mods, id)?;
+            (md.begin_stop()?, md.deactivate_fn(), md.get_lib())
+        };
+
+        self.fire_event(&ModuleEvent::ModuleStopping(id));
+
+        // we'll make a new activator if we start again.
+        let timeout = self.get_stop_timeout();
+        let this = Weak::clone(&self.this);
+        let reason = failure.clone();
+        let deactivated = activator.map_or(Ok(()), |a| {
+            let ctx = self.make_context(id, lib);
+            Dynamod::deactivate(deactivate_fn, a, ctx, timeout, move |stopped| {
+                if let Some(mod_manager) = this.upgrade() {
+                    let _ = mod_manager.finish_stop(
+                        id,
+                        reason,
+                        stopped.and(Err(Error::StopTimeout(id))),
+                    );
+                }
+            })
+        });
+        if let Err(Error::StopTimeout(_)) = deactivated {
+            // An abandoned module is still stopping, and can't be started again, until its
+            // stop returns: it then fails, see `finish_stop`.
+            return deactivated;
+        }
+
+        self.finish_stop(id, failure, deactivated)
+    }
+
+    // With a failure, the module ends up `Failed` even if it deactivated fine.
+    fn finish_stop(
+        &self,
+        id: DynamodId,
+        failure: Option<String>,
+        deactivated: Result<()>,
+    ) -> Result<()> {
+        let result = {
+            let mut mods = self.modules.lock();
+            let md = ModuleManager::get_mut(&mut mods, id)?;
+            // The module may have failed while stopping, e.g. in one of its listeners.
+            let deactivated = match failure.or_else(|| md.failure().map(str::to_owned)) {
+                Some(reason) => Err(Error::ModuleFailed(id, reason)),
+                None => deactivated,
+            };
+            md.finish_stop(deactivated)
+        };
+
+        self.release_module(id);
+
+        match result {
+            Ok(()) => self.fire_event(&ModuleEvent::ModuleStopped(id)),
+            Err(_) => self.fire_event(&ModuleEvent::ModuleFailed(id)),
+        }
+        self.persist();
+
+        result
+    }
+
+    // Data
+
+    pub fn get_data_root(&self) -> Option<PathBuf> {
+        self.data_root.lock().clone()
+    }
+
+    /// Modules get their data directory under `data_root`, named after their id.
+    pub fn set_data_root(&self, data_root: Option<PathBuf>) {
+        *self.data_root.lock() = data_root;
+    }
+
+    /// The module's private directory, created if needed. It is kept until the module is uninstalled.
+    pub fn data_dir(&self, id: DynamodId) -> Result<PathBuf> {
+        if !self.modules.lock().contains_key(&id) {
+            return Err(Error::ModuleNotFound(id));
+        }
+
+        let data_root = self
+            .get_data_root()
+            .ok_or_else(|| Error::from("No data root is configured for this container"))?;
+        let data_dir = data_root.join(id.to_string());
+        std::fs::create_dir_all(&data_dir)?;
+
+        Ok(data_dir)
+    }
+
+    pub fn get_cache_dir(&self) -> Option<PathBuf> {
+        self.cache_dir.lock().clone()
+    }
+
+    /// Module archives are extracted under `cache_dir`, in the temporary directory if none is set.
+    /// Each archive gets a private directory, but whoever can write to `cache_dir` itself
+    /// could still replace it: it should only be writable by the host.
+    pub fn set_cache_dir(&self, cache_dir: Option<PathBuf>) {
+        *self.cache_dir.lock() = cache_dir;
+    }
+
+    pub fn get_deploy_dir(&self) -> Option<PathBuf> {
+        self.deploy_dir.lock().clone()
+    }
+
+    /// The directory of the `Deployer`, the `Watcher` leaves its files alone.
+    pub fn set_deploy_dir(&self, deploy_dir: Option<PathBuf>) {
+        *self.deploy_dir.lock() = deploy_dir;
+    }
+
+    // Saved in the container state, which mustn't depend on the working directory.
+    // Symbolic links are kept: the deployer and the watcher follow the path they were given.
+    fn absolute_path(path: &str) -> Result<String> {
+        Ok(std::path::absolute(path)?.to_string_lossy().into_owned())
+    }
+
+    fn load(&self, path: &str) -> Result<Vec<Dynamod>> {
+        let cache_dir = self.get_cache_dir().unwrap_or_else(std::env::temp_dir);
+        Dynamod::load(path, &cache_dir, &self.trust_policy.read())
+    }
+
+    pub fn get_trust_policy(&self) -> TrustPolicy {
+        self.trust_policy.read().clone()
+    }
+
+    /// Module files are verified against `trust_policy` before they are loaded.
+    pub fn set_trust_policy(&self, trust_policy: TrustPolicy) {
+        *self.trust_policy.write() = trust_policy;
+    }
+
+    fn remove_data_dir(&self, id: DynamodId) {
+        if let Some(data_root) = self.get_data_root() {
+            let data_dir = data_root.join(id.to_string());
+            if data_dir.exists() {
+                if let Err(e) = std::fs::remove_dir_all(&data_dir) {
+                    warn!(
+                        module_id = id;
+                        "Couldn't remove the data of module #{} in {}: {:?}",
+                        id,
+                        data_dir.display(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn get_permission_policy(&self) -> PermissionPolicy {
+        self.permission_policy.read().clone()
+    }
+
+    /// Applies to the modules that start afterwards.
+    pub fn set_permission_policy(&self, policy: PermissionPolicy) {
+        *self.permission_policy.write() = policy;
+    }
+
+    pub fn get_stop_timeout(&self) -> Option<Duration> {
+        *self.stop_timeout.lock()
+    }
+
+    /// Past this timeout, a module that is still stopping is abandoned, it fails once its
+    /// stop returns.
+    pub fn set_stop_timeout(&self, stop_timeout: Option<Duration>) {
+        *self.stop_timeout.lock() = stop_timeout;
+    }
+
+    // Start levels
+
+    // Ascending start level, then install order.
+    fn ids_by_start_level<P>(&self, predicate: P) -> Vec<DynamodId>
+    where
+        P: Fn(&Dynamod) -> bool,
+    {
+        let mods = self.modules.lock();
+        let mut ids: Vec<(StartLevel, DynamodId)> = mods
+            .values()
+            .filter(|md| predicate(md))
+            .map(|md| (md.start_level(), md.id))
+            .collect();
+        ids.sort();
+        ids.into_iter().map(|(_, id)| id).collect()
+    }
+
+    // Starts the modules in order, carrying on after a failure and returning the first error.
+    fn start_ordered(self: &Arc<Self>, ids: Vec<DynamodId>) -> Result<()> {
+        let mut first_err = None;
+        for id in ids {
+            if let Err(e) = self.start(id) {
+                warn!(module_id = id; "Couldn't start module #{}: {:?}", id, e);
+                first_err.get_or_insert(e);
+            }
+        }
+        first_err.map_or(Ok(()), Err)
+    }
+
+    // Stops the modules in reverse order, carrying on after a failure and returning the first error.
+    fn stop_ordered(&self, ids: Vec<DynamodId>) -> Result<()> {
+        let mut first_err = None;
+        for id in ids.into_iter().rev() {
+            if let Err(e) = self.stop(id) {
+                warn!(module_id = id; "Couldn't stop module #{}: {:?}", id, e);
+                first_err.get_or_insert(e);
+            }
+        }
+        first_err.map_or(Ok(()), Err)
+    }
+
+    // Failed modules are left alone by bulk operations, they must be started explicitly.
+    // Lazy modules too, they start when one of their services is looked up.
+    fn is_startable(md: &Dynamod) -> bool {
+        matches!(md.state(), DynamodState::Installed | DynamodState::Resolved)
+            && !md.manifest().is_lazy()
+    }
+
+    /// Starts the lazy modules providing `svc_name` that are not active yet, returns whether
+    /// any of them started.
+    pub fn activate_lazy(self: &Arc<Self>, svc_name: &str) -> bool {
+        let ids = self.ids_by_start_level(|md| {
+            md.manifest().is_lazy()
+                && matches!(md.state(), DynamodState::Installed | DynamodState::Resolved)
+                && md.manifest().provides.iter().any(|name| name == svc_name)
+        });
+        if ids.is_empty() {
+            return false;
+        }
+
+        info!(
+            module_ids:? = ids,
+            service_name = svc_name;
+            "Activating modules {:?} for {}",
+            ids,
+            svc_name
+        );
+        let mut started = false;
+        for id in ids {
+            match self.start(id) {
+                Ok(()) => started = true,
+                Err(e) => warn!(module_id = id; "Couldn't activate lazy module #{}: {:?}", id, e),
+            }
+        }
+        started
+    }
+
+    pub fn get_start_level(&self) -> StartLevel {
+        *self.start_level.lock()
+    }
+
+    /// Stops the active modules above `start_level`, highest first, then starts
+    /// the modules up to `start_level`, lowest first.
+    pub fn set_start_level(self: &Arc<Self>, start_level: StartLevel) -> Result<()> {
+        *self.start_level.lock() = start_level;
+        self.persist();
+
+        let to_stop =
+            self.ids_by_start_level(|md| md.state().is_active() && md.start_level() > start_level);
+        let stopped = self.stop_ordered(to_stop);
+
+        let to_start = self.ids_by_start_level(|md| {
+            md.start_level() <= start_level && ModuleManager::is_startable(md)
+        });
+        let started = self.start_ordered(to_start);
+
+        stopped.and(started)
+    }
+
+    pub fn get_module_start_level(&self, id: DynamodId) -> Result<StartLevel> {
+        self.modules
+            .lock()
+            .get(&id)
+            .map(Dynamod::start_level)
+            .ok_or(Error::ModuleNotFound(id))
+    }
+
+    pub fn set_module_start_level(&self, id: DynamodId, start_level: StartLevel) -> Result<()> {
+        {
+            let mut mods = self.modules.lock();
+            ModuleManager::get_mut(&mut mods, id)?.set_start_level(start_level);
+        }
+        self.persist();
+        Ok(())
+    }
+
+    /// Starts every installed or resolved module but the lazy ones, by ascending start level.
+    pub fn start_all(self: &Arc<Self>) -> Result<()> {
+        let ids = self.ids_by_start_level(ModuleManager::is_startable);
+        self.start_ordered(ids)
+    }
+
+    /// Stops every active module, by descending start level.
+    pub fn stop_all(&self) -> Result<()> {
+        let ids = self.ids_by_start_level(|md| md.state().is_active());
+        self.stop_ordered(ids)
+    }
+
+    // Persistence
+
+    /// Saves the container state in `state_dir` after every change, `None` stops saving.
+    pub fn set_state_dir(&self, state_dir: Option<PathBuf>) {
+        *self.state_dir.lock() = state_dir;
+        self.persist();
+    }
+
+    pub fn get_container_state(&self) -> ContainerState {
+        let last_id = *self.curr_id.lock();
+        let start_level = self.get_start_level();
+        let mods = self.modules.lock();
+
+        ContainerState {
+            last_id,
+            start_level,
+            // static modules are installed again by the host.
+            modules: mods
+                .values()
+                .filter(|md| !md.is_static())
+                .map(|md| ModuleRecord {
+                    id: md.id,
+                    path: md.path.clone(),
+                    name: md.manifest().name.clone(),
+                    start_level: md.start_level(),
+                    started: md.state().is_active(),
+                })
+                .collect(),
+        }
+    }
+
+    // The lock on the state dir also orders concurrent saves.
+    fn persist(&self) {
+        let state_dir = self.state_dir.lock();
+        if let Some(ref state_dir) = *state_dir {
+            if let Err(e) = self.get_container_state().save(state_dir) {
+                warn!(
+                    "Couldn't save the container state in {}: {:?}",
+                    state_dir.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    /// Installs the modules of a saved state with their ids, then starts the ones that were
+    /// started. Modules that can't be installed anymore are left out.
+    /// Static modules aren't saved, the host may install them before or after the restore.
+    pub fn restore(self: &Arc<Self>, state: ContainerState) -> Result<Vec<DynamodId>> {
+        if self.modules.lock().values().any(|md| !md.is_static()) {
+            return Err(
+                "A container state can only be restored in a container without dynamic modules"
+                    .into(),
+            );
+        }
+
+        {
+            let max_id = state.modules.iter().map(|r| r.id).max().unwrap_or(0);
+            let mut curr_id = self.curr_id.lock();
+            *curr_id = (*curr_id).max(state.last_id).max(max_id);
+        }
+        *self.start_level.lock() = state.start_level;
+
+        let mut paths: Vec<&str> = Vec::new();
+        for record in state.modules.iter() {
+            if !paths.contains(&record.path.as_str()) {
+                paths.push(&record.path);
+            }
+        }
+
+        // Each library is loaded once, its modules are matched to the records by name.
+        let mut restored = Vec::new();
+        let mut started = Vec::new();
+        for path in paths {
+            let records: Vec<&ModuleRecord> =
+                state.modules.iter().filter(|r| r.path == path).collect();
+            let mut loaded = match self.load(path) {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    for record in records {
+                        warn!(
+                            module_id = record.id;
+                            "Couldn't restore module #{} from {}: {:?}",
+                            record.id, record.path, e
+                        );
+                    }
+                    continue;
+                }
+            };
+
+            for record in records {
+                // States saved before modules were named in records have one module per library.
+                let index = if record.name.is_empty() && loaded.len() == 1 {
+                    Some(0)
+                } else {
+                    loaded
+                        .iter()
+                        .position(|md| md.manifest().name == record.name)
+                };
+                match index {
+                    Some(index) => {
+                        let mut dyn_mod = loaded.remove(index);
+                        dyn_mod.id = record.id;
+                        dyn_mod.set_start_level(record.start_level);
+                        if record.started {
+                            started.push(dyn_mod.id);
+                        }
+                        restored.push(dyn_mod.id);
+                        self.insert(dyn_mod);
+                    }
+                    None => warn!(
+                        module_id = record.id;
+                        "Couldn't restore module #{}: {} doesn't declare module {}",
+                        record.id, record.path, record.name
+                    ),
+                }
+            }
+        }
+
+        self.resolve_installed(&restored);
+
+        // Lazy modules wait for their services to be looked up again.
+        let to_start =
+            self.ids_by_start_level(|md| started.contains(&md.id) && !md.manifest().is_lazy());
+        // Failures are logged, the other modules are still started.
+        let _ = self.start_ordered(to_start);
+
+        Ok(restored)
+    }
+
+    // Resolution
+
+    pub fn declare_host_service(&self, svc_name: &str) {
+        self.host_services.write().insert(svc_name.to_owned());
+        self.resolve_all();
+    }
+
+    fn available_services(&self, mods: &BTreeMap<DynamodId, Dynamod>) -> HashSet<String> {
+        let mut available = self.host_services.read().clone();
+        for md in mods.values() {
+            available.extend(md.manifest().provides.iter().cloned());
+        }
+        available
+    }
+
+    // Resolves an installed (or failed) module, does nothing if the module is already resolved.
+    pub fn resolve(&self, id: DynamodId) -> Result<()> {
+        {
+            let mut mods = self.modules.lock();
+            let available = self.available_services(&mods);
+            let md = ModuleManager::get_mut(&mut mods, id)?;
+
+            match md.state() {
+                DynamodState::Installed | DynamodState::Failed => (),
+                _ => return Ok(()),
+            }
+
+            let missing = md.manifest().missing_requirements(&available);
+            if !missing.is_empty() {
+                return Err(Error::Unresolved(id, missing));
+            }
+            md.set_state(DynamodState::Resolved)?;
+        }
+
+        self.fire_event(&ModuleEvent::ModuleResolved(id));
+
+        Ok(())
+    }
+
+    // Modules that don't resolve once (re)installed are reported right away.
+    fn resolve_installed(&self, ids: &[DynamodId]) {
+        self.resolve_all();
+        for id in ids {
+            if let Err(Error::Unresolved(_, missing)) = self.resolve(*id) {
+                warn!(module_id = *id; "Module #{} is unresolved, missing: {:?}", id, missing);
+                self.fire_event(&ModuleEvent::ModuleUnresolved(*id, missing));
+            }
+        }
+    }
+
+    // Resolves every installed module, and unresolves the resolved modules that lost a requirement.
+    // Modules that are already started are left alone. Only the changes are logged.
+    fn resolve_all(&self) {
+        let events = {
+            let mut mods = self.modules.lock();
+            let available = self.available_services(&mods);
+            let mut events = Vec::new();
+
+            for md in mods.values_mut() {
+                let missing = md.manifest().missing_requirements(&available);
+
+                let transition = match (md.state(), missing.is_empty()) {
+                    (DynamodState::Installed, true) => {
+                        Some((DynamodState::Resolved, ModuleEvent::ModuleResolved(md.id)))
+                    }
+                    (DynamodState::Resolved, false) => {
+                        warn!(
+                            module_id = md.id;
+                            "Module #{} is no longer resolved, missing: {:?}",
+                            md.id, missing
+                        );
+                        Some((
+                            DynamodState::Installed,
+                            ModuleEvent::ModuleUnresolved(md.id, missing),
+                        ))
+                    }
+                    _ => None,
+                };
+
+                if let Some((next, event)) = transition {
+                    if md.set_state(next).is_ok() {
+                        events.push(event);
+                    }
+                }
+            }
+            events
+        };
+
+        for event in events.iter() {
+            self.fire_event(event);
+        }
+    }
+
+    pub fn get_state(&self, id: DynamodId) -> Result<DynamodState> {
+        self.modules
+            .lock()
+            .get(&id)
+            .map(Dynamod::state)
+            .ok_or(Error::ModuleNotFound(id))
+    }
+
+    /// Installed modules with their path and the modification time of the loaded file.
+    pub fn get_module_files(&self) -> Vec<(DynamodId, String, Option<SystemTime>)> {
+        self.modules
+            .lock()
+            .values()
+            .filter(|md| !md.is_static())
+            .map(|md| (md.id, md.path.clone(), md.modified()))
+            .collect()
+    }
+
+    pub fn get_manifest(&self, id: DynamodId) -> Result<Manifest> {
+        self.modules
+            .lock()
+            .get(&id)
+            .map(|md| md.manifest().clone())
+            .ok_or(Error::ModuleNotFound(id))
+    }
+
+    /// Installed modules, by id.
+    pub fn modules(&self) -> Vec<ModuleInfo> {
+        let mut infos: Vec<ModuleInfo> = {
+            let mods = self.modules.lock();
+            mods.values()
+                .map(|md| ModuleInfo {
+                    id: md.id,
+                    path: md.path.clone(),
+                    state: md.state(),
+                    start_level: md.start_level(),
+                    failure: md.failure().map(str::to_owned),
+                    manifest: md.manifest().clone(),
+                    registered: Vec::new(),
+                    used: Vec::new(),
+                })
+                .collect()
+        };
+
+        if let Some(svc_manager) = self.svc_manager.upgrade() {
+            for info in infos.iter_mut() {
+                info.registered = svc_manager.get_services_ref_registered_by(info.id);
+                info.used = svc_manager.get_services_ref_used_by(info.id);
+            }
+        }
+
+        infos
+    }
+
+    pub fn print_installed_modules(&self) {
+        for info in self.modules() {
+            println!(
+                "#{} {} {} ({}) [{:?}]",
+                info.id, info.manifest.name, info.manifest.version, info.path, info.state
+            );
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use super::*;
+
+use hashbrown::HashSet;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    pub version: Version,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub provides: Vec<String>,
+    #[serde(default)]
+    pub requires: Vec<Requirement>,
+    #[serde(default)]
+    pub start_level: Option<StartLevel>,
+    #[serde(default)]
+    pub permissions: Option<ModulePermissions>,
+    #[serde(default)]
+    pub activation: Activation,
+}
+
+/// A lazy module is not started with the others, but on the first lookup by name
+/// of a service it provides, with one of the `Context::get_first_service*_by_name*` methods.
+/// Lookups by type or `TypeId`, `get_all_*` lookups and component references only see
+/// its services once something else started it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Activation {
+    #[default]
+    Eager,
+    Lazy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Requirement {
+    pub name: String,
+    #[serde(default)]
+    pub optional: bool,
+}
+
+impl Manifest {
+    pub fn from_json(json: &str) -> Result<Manifest> {
+        serde_json::from_str(json).map_err(|e| Error::InvalidManifest(e.to_string()))
+    }
+
+    pub fn new(name: &str, version: Version) -> Manifest {
+        Manifest {
+            name: name.to_owned(),
+            version,
+            description: String::new(),
+            provides: Vec::new(),
+            requires: Vec::new(),
+            start_level: None,
+            permissions: None,
+            activation: Activation::Eager,
+        }
+    }
+
+    /// Manifest used for modules that don't export one: named after the file, version 0.0.0.
+    pub fn from_path(path: &str) -> Manifest {
+        let name = std::path::Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_owned());
+
+        Manifest::new(&name, Version::new(0, 0, 0))
+    }
+
+    #[inline]
+    pub fn is_lazy(&self) -> bool {
+        self.activation == Activation::Lazy
+    }
+
+    /// Names of the mandatory requirements that are neither in `available` nor provided by this module.
+    pub fn missing_requirements(&self, available: &HashSet<String>) -> Vec<String> {
+        self.requires
+            .iter()
+            .filter(|req| !req.optional)
+            .filter(|req| !available.contains(&req.name) && !self.provides.contains(&req.name))
+            .map(|req| req.name.clone())
+            .collect()
+    }
+}
@@ -1,18 +1,69 @@
 use super::common::*;
 use super::service::*;
-use parking_lot::Mutex;
+use super::Error;
+use parking_lot::{Mutex, RwLock};
 use std::any::TypeId;
 use std::sync::{Arc, Weak};
 
+mod abi;
 mod activator;
+mod archive;
 mod container;
 mod context;
+mod declaration;
+mod deployer;
 mod dynamod;
+mod event;
+mod manager;
+mod manifest;
+mod permissions;
+mod persist;
+mod poller;
+mod state;
+mod verify;
+mod watcher;
 
 pub use super::Result;
 pub type DynamodId = u32;
+pub type StartLevel = u32;
+pub use self::abi::Abi;
+pub use self::abi::AbiFingerprint;
+pub use self::abi::FfiSlice;
+pub use self::abi::FfiStr;
+pub use self::activator::default_deactivate;
 pub use self::activator::ActivateFn;
 pub use self::activator::Activator;
+pub use self::activator::DeactivateFn;
+pub use self::archive::ModuleArchive;
+pub use self::archive::ARCHIVE_EXTENSION;
 pub use self::container::Container;
 pub use self::context::Context;
+pub use self::declaration::init_log;
+pub use self::declaration::InitLogFn;
+pub use self::declaration::ModuleDecl;
+pub use self::declaration::ModuleEntry;
+pub use self::declaration::ModuleVTable;
+pub use self::declaration::MODULE_DECL_VERSION;
+pub use self::deployer::Deployer;
 pub use self::dynamod::Dynamod;
+pub use self::dynamod::DynamodLib;
+pub use self::dynamod::ModuleInfo;
+pub use self::dynamod::ZombieDynamod;
+pub use self::dynamod::ZombieInfo;
+pub use self::event::ModuleEvent;
+pub use self::event::ModuleEventListener;
+pub use self::manager::ModuleManager;
+pub use self::manager::DEFAULT_START_LEVEL;
+pub use self::manager::FIRST_STATIC_ID;
+pub use self::manifest::Activation;
+pub use self::manifest::Manifest;
+pub use self::manifest::Requirement;
+pub use self::permissions::ModulePermissions;
+pub use self::permissions::Permission;
+pub use self::permissions::PermissionPolicy;
+pub use self::persist::ContainerState;
+pub use self::persist::ModuleRecord;
+pub use self::state::DynamodState;
+pub use self::verify::TrustPolicy;
+pub use self::verify::VerificationError;
+pub use self::watcher::Watcher;
@@ -0,0 +1,111 @@
+use super::*;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+    Register,
+    Get,
+    Listen,
+}
+
+/// Which services a module may register, get and listen to, by service name.
+///
+/// A pattern is either a service name, a prefix ending with `*` (e.g. `example_api::*`) or `*`.
+/// A missing list doesn't restrict anything, an empty one denies everything.
+/// Services looked up by type are checked against the name they were registered under.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ModulePermissions {
+    #[serde(default)]
+    pub register: Option<Vec<String>>,
+    #[serde(default)]
+    pub get: Option<Vec<String>>,
+    #[serde(default)]
+    pub listen: Option<Vec<String>>,
+}
+
+impl ModulePermissions {
+    pub fn allows(&self, permission: Permission, svc_name: &str) -> bool {
+        self.patterns(permission).is_none_or(|patterns| {
+            patterns
+                .iter()
+                .any(|p| ModulePermissions::matches(p, svc_name))
+        })
+    }
+
+    /// Whether `permission` is granted for at least one service.
+    pub fn allows_any(&self, permission: Permission) -> bool {
+        self.patterns(permission)
+            .is_none_or(|patterns| !patterns.is_empty())
+    }
+
+    fn patterns(&self, permission: Permission) -> Option<&Vec<String>> {
+        match permission {
+            Permission::Register => self.register.as_ref(),
+            Permission::Get => self.get.as_ref(),
+            Permission::Listen => self.listen.as_ref(),
+        }
+    }
+
+    fn matches(pattern: &str, svc_name: &str) -> bool {
+        match pattern.strip_suffix('*') {
+            Some(prefix) => svc_name.starts_with(prefix),
+            None => pattern == svc_name,
+        }
+    }
+}
+
+/// Permissions granted by the host. A module only gets what both its manifest asks for
+/// and the policy grants it: the entry of the file it was loaded from if there is one,
+/// the default one otherwise.
+///
+/// Entries are keyed by path rather than by module name, which the module chooses itself.
+/// Relative paths are resolved against the working directory, static modules are keyed
+/// by `static:<name>`. Every module of a file gets the entry of that file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PermissionPolicy {
+    #[serde(default)]
+    pub default: Option<ModulePermissions>,
+    #[serde(default)]
+    pub paths: BTreeMap<String, ModulePermissions>,
+}
+
+impl PermissionPolicy {
+    pub fn load(path: &Path) -> Result<PermissionPolicy> {
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(|e| {
+            Error::StrError(format!(
+                "Invalid permission policy {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// All the permissions that apply to a module loaded from `path` with this manifest.
+    pub fn permissions_of(&self, path: &str, manifest: &Manifest) -> Vec<ModulePermissions> {
+        let granted = self
+            .paths
+            .iter()
+            .find(|(key, _)| PermissionPolicy::is_same_path(key, path))
+            .map(|(_, permissions)| permissions)
+            .or(self.default.as_ref());
+
+        manifest
+            .permissions
+            .iter()
+            .chain(granted)
+            .cloned()
+            .collect()
+    }
+
+    fn is_same_path(key: &str, path: &str) -> bool {
+        if key.starts_with("static:") || path.starts_with("static:") {
+            return key == path;
+        }
+        std::path::absolute(key).is_ok_and(|key| key == Path::new(path))
+    }
+}
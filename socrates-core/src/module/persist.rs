@@ -0,0 +1,59 @@
+use super::*;
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const STATE_FILE: &str = "container.json";
+
+/// What a container needs to bring back its modules after a restart.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContainerState {
+    pub last_id: DynamodId,
+    pub start_level: StartLevel,
+    pub modules: Vec<ModuleRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleRecord {
+    pub id: DynamodId,
+    pub path: String,
+    #[serde(default)]
+    pub name: String,
+    pub start_level: StartLevel,
+    pub started: bool,
+}
+
+impl ContainerState {
+    fn state_file(state_dir: &Path) -> PathBuf {
+        state_dir.join(STATE_FILE)
+    }
+
+    /// Reads the state saved in `state_dir`, if any.
+    pub fn load(state_dir: &Path) -> Result<Option<ContainerState>> {
+        let path = ContainerState::state_file(state_dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let json = fs::read_to_string(&path)?;
+        serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|e| Error::InvalidContainerState(e.to_string()))
+    }
+
+    // Written to a temporary file first, so that a crash never leaves a truncated state.
+    pub fn save(&self, state_dir: &Path) -> Result<()> {
+        fs::create_dir_all(state_dir)?;
+
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::InvalidContainerState(e.to_string()))?;
+
+        let path = ContainerState::state_file(state_dir);
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, json)?;
+        fs::rename(&tmp_path, &path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use super::*;
+
+use hashbrown::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Calls `poll` on its own thread every interval, until it is dropped or the container is gone.
+pub(crate) struct Poller {
+    running: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Poller {
+    pub fn spawn<F>(
+        name: &str,
+        mod_manager: Weak<ModuleManager>,
+        interval: Duration,
+        mut poll: F,
+    ) -> Result<Poller>
+    where
+        F: FnMut(&Arc<ModuleManager>) + Send + 'static,
+    {
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = {
+            let running = Arc::clone(&running);
+            thread::Builder::new()
+                .name(name.to_owned())
+                .spawn(move || {
+                    while running.load(Ordering::SeqCst) {
+                        // Not kept while parked, the container may go away in the meantime.
+                        match mod_manager.upgrade() {
+                            Some(mod_manager) => poll(&mod_manager),
+                            None => break,
+                        }
+                        thread::park_timeout(interval);
+                    }
+                })?
+        };
+
+        Ok(Poller {
+            running,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Files whose change is not picked up yet: a change is only picked up once the file
+/// stayed the same for a full interval, so that a module is not loaded while it is
+/// still being written.
+#[derive(Default)]
+pub(crate) struct Pending(HashMap<PathBuf, SystemTime>);
+
+impl Pending {
+    /// Whether `path` was already `modified` at the previous poll, it is pending otherwise.
+    pub fn is_stable(&mut self, path: &Path, modified: SystemTime) -> bool {
+        if self.0.get(path) == Some(&modified) {
+            self.0.remove(path);
+            true
+        } else {
+            self.0.insert(path.to_owned(), modified);
+            false
+        }
+    }
+
+    pub fn forget(&mut self, path: &Path) {
+        self.0.remove(path);
+    }
+
+    pub fn retain<F: Fn(&Path) -> bool>(&mut self, keep: F) {
+        self.0.retain(|path, _| keep(path));
+    }
+}
+
+/// The files of the installed modules that `include` accepts, with the modules loaded
+/// from each and the modification time of the file when they were loaded.
+/// The modules of a library share its modification time.
+pub(crate) fn module_files<F: Fn(&Path) -> bool>(
+    mod_manager: &ModuleManager,
+    include: F,
+) -> HashMap<PathBuf, (Vec<DynamodId>, Option<SystemTime>)> {
+    let mut files: HashMap<PathBuf, (Vec<DynamodId>, Option<SystemTime>)> = HashMap::new();
+    for (id, path, loaded) in mod_manager.get_module_files() {
+        let path = PathBuf::from(path);
+        if include(&path) {
+            files.entry(path).or_insert((Vec::new(), loaded)).0.push(id);
+        }
+    }
+    files
+}
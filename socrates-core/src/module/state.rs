@@ -0,0 +1,39 @@
+/// Lifecycle state of a `Dynamod`.
+/// See `can_transition_to` for the transitions enforced by the container.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DynamodState {
+    Installed,
+    Resolved,
+    Starting,
+    Active,
+    Stopping,
+    Failed,
+    Zombie,
+}
+
+impl DynamodState {
+    pub fn can_transition_to(self, next: DynamodState) -> bool {
+        use self::DynamodState::*;
+
+        matches!(
+            (self, next),
+            (Installed, Resolved)
+                | (Failed, Resolved)
+                | (Resolved, Installed)
+                | (Resolved, Starting)
+                | (Starting, Active)
+                | (Starting, Failed)
+                | (Active, Stopping)
+                | (Stopping, Resolved)
+                | (Stopping, Failed)
+                | (Installed, Zombie)
+                | (Resolved, Zombie)
+                | (Failed, Zombie)
+        )
+    }
+
+    #[inline]
+    pub fn is_active(self) -> bool {
+        self == DynamodState::Active
+    }
+}
@@ -0,0 +1,164 @@
+use super::*;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use sha2::{Digest, Sha256};
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+
+/// Why a module file was refused, see `TrustPolicy`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationError {
+    UntrustedDigest(String),
+    InvalidDigestFile(String),
+    DigestMismatch { expected: String, found: String },
+    MissingSignature,
+    InvalidSignatureFile(String),
+    UntrustedSignature,
+}
+
+/// Checks module files before they are loaded.
+///
+/// With trusted digests, the SHA-256 digest of every module file must be one of them.
+/// With trusted keys, every module file must be signed by one of them, in `<file>.sig`:
+/// an ed25519 signature of the file, raw or hex. When both are set, both must pass.
+///
+/// A `<file>.sha256` next to the file, as written by `sha256sum`, is checked when there is
+/// one. It only catches corrupted files: whoever can replace the file can replace it as well.
+#[derive(Debug, Clone, Default)]
+pub struct TrustPolicy {
+    pub trusted_digests: Vec<[u8; 32]>,
+    pub trusted_keys: Vec<VerifyingKey>,
+}
+
+impl TrustPolicy {
+    /// Trusts the module files with this SHA-256 digest, given in hex.
+    pub fn trust_digest_hex(&mut self, digest: &str) -> Result<()> {
+        let digest: [u8; 32] = decode_hex(digest)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| format!("Invalid SHA-256 digest: {}", digest))?;
+        self.trusted_digests.push(digest);
+
+        Ok(())
+    }
+
+    /// Trusts the ed25519 public key given in hex.
+    pub fn trust_key_hex(&mut self, key: &str) -> Result<()> {
+        let key: [u8; 32] = decode_hex(key)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| format!("Invalid ed25519 public key: {}", key))?;
+        let key = VerifyingKey::from_bytes(&key)
+            .map_err(|e| format!("Invalid ed25519 public key: {}", e))?;
+        self.trusted_keys.push(key);
+
+        Ok(())
+    }
+
+    /// Verifies `bytes`, the content of the module file at `path`.
+    pub fn verify(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        let digest: [u8; 32] = Sha256::digest(bytes).into();
+        self.check_digest_file(path, &digest)
+            .and_then(|()| self.check_trusted_digest(&digest))
+            .and_then(|()| self.check_signature(path, bytes))
+            .map_err(|e| Error::VerificationFailed(path.display().to_string(), e))
+    }
+
+    fn check_digest_file(
+        &self,
+        path: &Path,
+        digest: &[u8; 32],
+    ) -> std::result::Result<(), VerificationError> {
+        let digest_path = sidecar(path, "sha256");
+        let expected = match std::fs::read_to_string(&digest_path) {
+            Ok(content) => content
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase(),
+            Err(_) if !digest_path.exists() => return Ok(()),
+            Err(e) => return Err(VerificationError::InvalidDigestFile(e.to_string())),
+        };
+        if decode_hex(&expected).map(|d| d.len()) != Some(32) {
+            return Err(VerificationError::InvalidDigestFile(format!(
+                "not a SHA-256 digest: {}",
+                expected
+            )));
+        }
+
+        let found = encode_hex(digest);
+        if found != expected {
+            return Err(VerificationError::DigestMismatch { expected, found });
+        }
+
+        Ok(())
+    }
+
+    fn check_trusted_digest(
+        &self,
+        digest: &[u8; 32],
+    ) -> std::result::Result<(), VerificationError> {
+        if self.trusted_digests.is_empty() || self.trusted_digests.contains(digest) {
+            return Ok(());
+        }
+
+        Err(VerificationError::UntrustedDigest(encode_hex(digest)))
+    }
+
+    fn check_signature(
+        &self,
+        path: &Path,
+        bytes: &[u8],
+    ) -> std::result::Result<(), VerificationError> {
+        if self.trusted_keys.is_empty() {
+            return Ok(());
+        }
+
+        let signature_path = sidecar(path, "sig");
+        let content = match std::fs::read(&signature_path) {
+            Ok(content) => content,
+            Err(_) if !signature_path.exists() => return Err(VerificationError::MissingSignature),
+            Err(e) => return Err(VerificationError::InvalidSignatureFile(e.to_string())),
+        };
+        let signature: [u8; 64] = match content.len() {
+            64 => content.try_into().ok(),
+            _ => std::str::from_utf8(&content)
+                .ok()
+                .and_then(|hex| decode_hex(hex.trim()))
+                .and_then(|bytes| bytes.try_into().ok()),
+        }
+        .ok_or_else(|| {
+            VerificationError::InvalidSignatureFile("not an ed25519 signature".to_owned())
+        })?;
+        let signature = Signature::from_bytes(&signature);
+
+        let trusted = self
+            .trusted_keys
+            .iter()
+            .any(|key| key.verify_strict(bytes, &signature).is_ok());
+        if !trusted {
+            return Err(VerificationError::UntrustedSignature);
+        }
+
+        Ok(())
+    }
+}
+
+fn sidecar(path: &Path, extension: &str) -> PathBuf {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".");
+    sidecar.push(extension);
+    PathBuf::from(sidecar)
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
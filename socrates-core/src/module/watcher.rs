@@ -0,0 +1,74 @@
+use super::poller::{module_files, Pending, Poller};
+use super::*;
+
+use log::{info, warn};
+use std::time::{Duration, SystemTime};
+
+/// Polls the files of installed modules and reloads the modules whose file changed.
+/// The modules of a library are reloaded together.
+///
+/// A change is only picked up once the file stayed the same for a full interval,
+/// so that a module is not loaded while it is still being written.
+/// Files in the deploy directory are left to the `Deployer`.
+pub struct Watcher {
+    _poller: Poller,
+}
+
+impl Watcher {
+    pub fn spawn(mod_manager: Weak<ModuleManager>, interval: Duration) -> Result<Watcher> {
+        let mut pending = Pending::default();
+        let poller = Poller::spawn(
+            "socrates-watcher",
+            mod_manager,
+            interval,
+            move |mod_manager| Watcher::scan(mod_manager, &mut pending),
+        )?;
+
+        Ok(Watcher { _poller: poller })
+    }
+
+    fn scan(mod_manager: &Arc<ModuleManager>, pending: &mut Pending) {
+        let deploy_dir = mod_manager.get_deploy_dir();
+        let files = module_files(mod_manager, |path| path.parent() != deploy_dir.as_deref());
+
+        for (path, (ids, loaded)) in files.iter() {
+            let modified = std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+
+            match modified {
+                Some(modified) if Some(modified) != *loaded => {
+                    if pending.is_stable(path, modified) {
+                        Watcher::reload(mod_manager, ids, &path.to_string_lossy(), modified);
+                    }
+                }
+                _ => pending.forget(path),
+            }
+        }
+
+        pending.retain(|path| files.contains_key(path));
+    }
+
+    // Reloading a module reloads the modules sharing its library, they are skipped.
+    fn reload(
+        mod_manager: &Arc<ModuleManager>,
+        ids: &[DynamodId],
+        path: &str,
+        modified: SystemTime,
+    ) {
+        for id in ids {
+            let reloaded = mod_manager
+                .get_module_files()
+                .iter()
+                .any(|(other, _, loaded)| other == id && *loaded == Some(modified));
+            if reloaded {
+                continue;
+            }
+
+            info!(module_id = *id; "Module #{} changed on disk, reloading {}", id, path);
+            if let Err(e) = mod_manager.reload(*id) {
+                warn!(module_id = *id; "Couldn't reload module #{}: {:?}", id, e);
+            }
+        }
+    }
+}
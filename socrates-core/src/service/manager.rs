@@ -1,16 +1,81 @@
 use super::*;
 
+use hashbrown::HashMap;
+
 #[derive(Default)]
 pub struct ServiceManager {
     pub registry: RwLock<ServiceRegistry>,
     pub listeners: RwLock<ServiceListeners>,
+    mod_manager: RwLock<Weak<ModuleManager>>,
+    permissions: RwLock<HashMap<DynamodId, Vec<ModulePermissions>>>,
 }
 
 impl ServiceManager {
-    pub fn register_listener(&self, listener: WeakListener<ServiceEvent>) {
+    // To fail the modules whose listeners panic.
+    pub fn set_module_manager(&self, mod_manager: Weak<ModuleManager>) {
+        *self.mod_manager.write() = mod_manager;
+    }
+
+    // Permissions
+
+    /// Restricts what `module_id` can do, an empty list lifts all restrictions.
+    pub fn set_permissions(&self, module_id: DynamodId, permissions: Vec<ModulePermissions>) {
+        let mut all = self.permissions.write();
+        if permissions.is_empty() {
+            all.remove(&module_id);
+        } else {
+            all.insert(module_id, permissions);
+        }
+    }
+
+    pub fn is_allowed(&self, module_id: DynamodId, permission: Permission, svc_name: &str) -> bool {
+        self.permissions
+            .read()
+            .get(&module_id)
+            .is_none_or(|all| all.iter().all(|p| p.allows(permission, svc_name)))
+    }
+
+    pub fn is_allowed_any(&self, module_id: DynamodId, permission: Permission) -> bool {
+        self.permissions
+            .read()
+            .get(&module_id)
+            .is_none_or(|all| all.iter().all(|p| p.allows_any(permission)))
+    }
+
+    pub fn check_permission(
+        &self,
+        module_id: DynamodId,
+        permission: Permission,
+        svc_name: &str,
+    ) -> Result<()> {
+        if self.is_allowed(module_id, permission, svc_name) {
+            Ok(())
+        } else {
+            Err(Error::PermissionDenied(
+                module_id,
+                permission,
+                svc_name.to_owned(),
+            ))
+        }
+    }
+
+    /// Whether `module_id` may get the service `svc_id`.
+    pub fn is_visible(&self, module_id: DynamodId, svc_id: ServiceId) -> bool {
+        if !self.permissions.read().contains_key(&module_id) {
+            return true;
+        }
+        self.get_service_ref(svc_id)
+            .is_some_and(|svc_ref| self.is_allowed(module_id, Permission::Get, &svc_ref.name))
+    }
+
+    pub fn register_listener(
+        &self,
+        listener: WeakListener<ServiceEvent>,
+        owner: Option<DynamodId>,
+    ) {
         let mut listeners = self.listeners.write();
 
-        listeners.insert_listener(listener);
+        listeners.insert_listener(listener, owner);
     }
 
     pub fn unregister_service(&self, svc_id: ServiceId) {
@@ -21,6 +86,42 @@ impl ServiceManager {
         }
     }
 
+    /// Reclaims what a stopped module left behind: unregisters its services, releases
+    /// the services it uses and removes its listeners. Every leaked handle is logged.
+    pub fn release_module(&self, owner_id: DynamodId) {
+        let unregistered = self.registry.write().unregister_services_owned_by(owner_id);
+        for service_ref in unregistered.iter() {
+            warn!(
+                module_id = owner_id,
+                service_id = service_ref.core.id,
+                service_name = service_ref.name.as_str();
+                "Module #{} leaked the registration of {:?}, unregistering it",
+                owner_id, service_ref
+            );
+            self.fire_event(&ServiceEvent::ServiceUnregistered(service_ref.clone()));
+        }
+
+        let released = self.registry.write().remove_uses_by(owner_id);
+        for (service_ref, count) in released.iter() {
+            warn!(
+                module_id = owner_id,
+                service_id = service_ref.core.id,
+                service_name = service_ref.name.as_str();
+                "Module #{} leaked {} use(s) of {:?}, releasing them",
+                owner_id, count, service_ref
+            );
+        }
+
+        let listeners = self.listeners.write().remove_owned_by(owner_id);
+        if listeners > 0 {
+            warn!(
+                module_id = owner_id;
+                "Module #{} leaked {} service listener(s), removing them",
+                owner_id, listeners
+            );
+        }
+    }
+
     // Register
 
     pub fn register_service(
@@ -29,14 +130,18 @@ impl ServiceManager {
         svc_name: &str,
         svc_ranking: ServiceRanking,
         owner_id: DynamodId,
+        owner_lib: Option<Arc<DynamodLib>>,
         svc: Box<dyn Service>,
     ) -> Result<ServiceRef> {
+        self.check_permission(owner_id, Permission::Register, svc_name)?;
+
         let service_ref = self.registry.write().register_service(
             svc_type_id,
             svc_name,
             svc.into(),
             svc_ranking,
             owner_id,
+            owner_lib,
         );
 
         self.fire_event(&ServiceEvent::ServiceRegistered(service_ref.clone()));
@@ -46,10 +151,25 @@ impl ServiceManager {
 
     fn fire_event(&self, event: &ServiceEvent) {
         let listeners = self.listeners.read().clone();
-        let dirty = listeners.fire_event(event);
+        let svc_name = &event.get_service_ref().name;
+        let (dirty, panics) = listeners.fire_event_to(event, |owner| {
+            owner.is_none_or(|id| self.is_allowed(id, Permission::Listen, svc_name))
+        });
         if dirty {
             self.listeners.write().clean_up();
         }
+
+        if !panics.is_empty() {
+            let mod_manager = self.mod_manager.read().upgrade();
+            match mod_manager {
+                Some(mod_manager) => mod_manager.report_panics(panics),
+                None => {
+                    for (_, message) in panics {
+                        error!("A service listener panicked: {}", message);
+                    }
+                }
+            }
+        }
     }
 
     // By ServiceId
@@ -64,6 +184,9 @@ impl ServiceManager {
         svc_id: ServiceId,
         user_id: DynamodId,
     ) -> Option<Weak<dyn Service>> {
+        if !self.is_visible(user_id, svc_id) {
+            return None;
+        }
         self.registry.write().get_service_object(svc_id, user_id)
     }
 
@@ -127,36 +250,39 @@ impl ServiceManager {
             })
     }
 
-    pub fn remove_use(&self, svc_id: ServiceId, user_id: DynamodId) {
-        self.registry.write().remove_use(svc_id, user_id);
+    pub fn get_services_ref_registered_by(&self, owner_id: DynamodId) -> Vec<ServiceRef> {
+        self.registry
+            .read()
+            .get_services_ref_registered_by(owner_id)
     }
-}
 
-use im::vector::Vector;
+    pub fn get_services_ref_used_by(&self, user_id: DynamodId) -> Vec<(ServiceRef, u32)> {
+        self.registry.read().get_services_ref_used_by(user_id)
+    }
 
-#[derive(Default, Clone)]
-pub struct ServiceListeners {
-    listeners: Vector<WeakListener<ServiceEvent>>,
-}
-impl ServiceListeners {
-    pub fn insert_listener(&mut self, listener: WeakListener<ServiceEvent>) {
-        self.clean_up();
-        self.listeners.push_back(listener);
+    pub fn get_users_of_services_owned_by(&self, owner_id: DynamodId) -> Vec<DynamodId> {
+        self.registry
+            .read()
+            .get_users_of_services_owned_by(owner_id)
     }
 
-    pub fn clean_up(&mut self) {
-        self.listeners.retain(WeakListener::is_alive);
+    pub fn get_zombie_users(&self) -> Vec<DynamodId> {
+        self.registry.read().get_zombie_users()
     }
 
-    // returns dirty if it should be cleaned.
-    pub fn fire_event(&self, event: &ServiceEvent) -> bool {
-        let mut dirty = false;
-        for listener in self.listeners.iter() {
-            let was_fired = listener.fire_event(event);
-            if !was_fired {
-                dirty = true;
-            }
-        }
-        dirty
+    pub fn get_zombie_services(
+        &self,
+        owner_id: DynamodId,
+        owner_lib: &Weak<DynamodLib>,
+    ) -> Vec<(ServiceRef, Vec<DynamodId>)> {
+        self.registry
+            .read()
+            .get_zombie_services(owner_id, owner_lib)
+    }
+
+    pub fn remove_use(&self, svc_id: ServiceId, user_id: DynamodId) {
+        self.registry.write().remove_use(svc_id, user_id);
     }
 }
+
+pub type ServiceListeners = Listeners<ServiceEvent>;
@@ -1,3 +1,4 @@
+use log::{error, warn};
 use parking_lot::RwLock;
 use std::any::TypeId;
 use std::sync::{Arc, Weak};
@@ -13,6 +14,7 @@ mod svc;
 
 use super::common::*;
 use super::module::*;
+use super::Error;
 
 pub use self::event::ServiceEvent;
 pub use self::event::ServiceEventListener;
@@ -1,4 +1,5 @@
 use hashbrown::HashMap;
+use log::debug;
 
 use super::*;
 
@@ -8,7 +9,11 @@ pub struct RegisteredService {
     name: Arc<str>,
     owner_id: DynamodId,
     used_by_count: HashMap<DynamodId, u32>,
-    service_object: Arc<dyn Service>, // the "master" strong ref
+    // Uses released for modules that stopped without dropping them: the handles may still be
+    // alive, so the service and the library holding its code are kept until they are dropped.
+    leaked_count: u32,
+    service_object: Arc<dyn Service>,   // the "master" strong ref
+    owner_lib: Option<Arc<DynamodLib>>, // must be last to be dropped last
 }
 
 impl RegisteredService {
@@ -20,6 +25,21 @@ impl RegisteredService {
             owner_id: self.owner_id,
         }
     }
+
+    fn is_in_use(&self) -> bool {
+        !self.used_by_count.is_empty() || self.leaked_count > 0
+    }
+
+    fn log_dropped(&self, what: &str) {
+        debug!(
+            service_id = self.core_props.id,
+            service_name = &*self.name,
+            module_id = self.owner_id;
+            "{}: {:?}",
+            what,
+            self.make_service_ref()
+        );
+    }
 }
 
 impl From<&RegisteredService> for ServiceRef {
@@ -51,6 +71,7 @@ impl ServiceRegistry {
         service_object: Arc<dyn Service>,
         svc_ranking: ServiceRanking,
         owner_id: DynamodId,
+        owner_lib: Option<Arc<DynamodLib>>,
     ) -> ServiceRef {
         let new_id = self.curr_id + 1;
 
@@ -63,7 +84,9 @@ impl ServiceRegistry {
             name: svc_name.into(),
             owner_id,
             used_by_count: HashMap::new(),
+            leaked_count: 0,
             service_object,
+            owner_lib,
         };
 
         let service_ref = service.make_service_ref();
@@ -84,20 +107,30 @@ impl ServiceRegistry {
 
     pub fn unregister_service(&mut self, svc_id: ServiceId) -> Option<ServiceRef> {
         if let Some(rs) = self.by_service_id.remove(&svc_id) {
-            self.by_name.remove(&rs.name).expect("unsynced registry!");
-            self.by_type_id
-                .remove(&rs.type_id)
+            // Other services may be registered under the same name or type.
+            let svcs_using_name = self.by_name.get_mut(&rs.name).expect("unsynced registry!");
+            svcs_using_name.remove(&rs.core_props);
+            if svcs_using_name.is_empty() {
+                self.by_name.remove(&rs.name);
+            }
+
+            let svcs_using_type_id = self
+                .by_type_id
+                .get_mut(&rs.type_id)
                 .expect("unsynced registry!");
+            svcs_using_type_id.remove(&rs.core_props);
+            if svcs_using_type_id.is_empty() {
+                self.by_type_id.remove(&rs.type_id);
+            }
 
             let svc_ref = rs.make_service_ref();
 
             // If there are still users
-            if !rs.used_by_count.is_empty() {
+            if rs.is_in_use() {
                 // We don't drop the service but make it unavailable for queries.
                 self.zombies.insert(svc_id, rs);
             } else {
-                // TODO move to logging
-                println!("Dropping service (no users): {:?}", rs.make_service_ref());
+                rs.log_dropped("Dropping service (no users)");
             }
 
             Some(svc_ref)
@@ -146,33 +179,143 @@ impl ServiceRegistry {
         })
     }
 
-    pub fn remove_use(&mut self, svc_id: ServiceId, user_id: DynamodId) {
-        if let Some(rs) = self.by_service_id.get_mut(&svc_id) {
-            if ServiceRegistry::decrement_use(rs, user_id) == Some(0) {
-                rs.used_by_count.remove(&user_id);
+    /// Services currently registered by `owner_id`, by service id.
+    pub fn get_services_ref_registered_by(&self, owner_id: DynamodId) -> Vec<ServiceRef> {
+        let mut refs: Vec<ServiceRef> = self
+            .by_service_id
+            .values()
+            .filter(|rs| rs.owner_id == owner_id)
+            .map(RegisteredService::make_service_ref)
+            .collect();
+        refs.sort_by_key(|svc_ref| svc_ref.core.id);
+        refs
+    }
+
+    /// Services used by `user_id` with the use count, including unregistered ones, by service id.
+    pub fn get_services_ref_used_by(&self, user_id: DynamodId) -> Vec<(ServiceRef, u32)> {
+        let mut refs: Vec<(ServiceRef, u32)> = self
+            .by_service_id
+            .values()
+            .chain(self.zombies.values())
+            .filter_map(|rs| {
+                rs.used_by_count
+                    .get(&user_id)
+                    .map(|count| (rs.make_service_ref(), *count))
+            })
+            .collect();
+        refs.sort_by_key(|(svc_ref, _)| svc_ref.core.id);
+        refs
+    }
+
+    /// Modules using services registered by `owner_id`, including unregistered ones.
+    pub fn get_users_of_services_owned_by(&self, owner_id: DynamodId) -> Vec<DynamodId> {
+        let mut users: Vec<DynamodId> = self
+            .by_service_id
+            .values()
+            .chain(self.zombies.values())
+            .filter(|rs| rs.owner_id == owner_id)
+            .flat_map(|rs| rs.used_by_count.keys().cloned())
+            .filter(|user_id| *user_id != owner_id)
+            .collect();
+        users.sort();
+        users.dedup();
+        users
+    }
+
+    /// Modules still using unregistered services.
+    pub fn get_zombie_users(&self) -> Vec<DynamodId> {
+        let mut users: Vec<DynamodId> = self
+            .zombies
+            .values()
+            .flat_map(|rs| rs.used_by_count.keys().cloned())
+            .collect();
+        users.sort();
+        users.dedup();
+        users
+    }
+
+    /// Unregistered services of `owner_id` from the given library (a module keeps its id across
+    /// updates) that are still in use, with their users.
+    pub fn get_zombie_services(
+        &self,
+        owner_id: DynamodId,
+        owner_lib: &Weak<DynamodLib>,
+    ) -> Vec<(ServiceRef, Vec<DynamodId>)> {
+        self.zombies
+            .values()
+            .filter(|rs| rs.owner_id == owner_id)
+            .filter(|rs| {
+                rs.owner_lib
+                    .as_ref()
+                    .is_some_and(|lib| std::ptr::eq(Arc::as_ptr(lib), owner_lib.as_ptr()))
+            })
+            .map(|rs| {
+                let mut users: Vec<DynamodId> = rs.used_by_count.keys().cloned().collect();
+                users.sort();
+                (rs.make_service_ref(), users)
+            })
+            .collect()
+    }
+
+    /// Unregisters every service still registered by `owner_id`.
+    pub fn unregister_services_owned_by(&mut self, owner_id: DynamodId) -> Vec<ServiceRef> {
+        let owned: Vec<ServiceId> = self
+            .by_service_id
+            .values()
+            .filter(|rs| rs.owner_id == owner_id)
+            .map(|rs| rs.core_props.id)
+            .collect();
+
+        owned
+            .into_iter()
+            .flat_map(|svc_id| self.unregister_service(svc_id))
+            .collect()
+    }
+
+    /// Removes all the uses of `user_id`, returns the services it was still using with the use count.
+    /// The handles may have been leaked and outlive the module: the services are only dropped
+    /// once they are.
+    pub fn remove_uses_by(&mut self, user_id: DynamodId) -> Vec<(ServiceRef, u32)> {
+        let mut released = Vec::new();
+
+        for rs in self
+            .by_service_id
+            .values_mut()
+            .chain(self.zombies.values_mut())
+        {
+            if let Some(count) = rs.used_by_count.remove(&user_id) {
+                rs.leaked_count += count;
+                released.push((rs.make_service_ref(), count));
             }
+        }
+
+        released
+    }
 
-            self.by_name
-                .get_mut(&rs.name)
-                .map(|v| v.remove(&rs.core_props));
+    pub fn remove_use(&mut self, svc_id: ServiceId, user_id: DynamodId) {
+        if let Some(rs) = self.by_service_id.get_mut(&svc_id) {
+            ServiceRegistry::decrement_use(rs, user_id);
         } else if let Some(rs) = self.zombies.get_mut(&svc_id) {
-            if ServiceRegistry::decrement_use(rs, user_id) == Some(0) {
-                rs.used_by_count.remove(&user_id);
-
-                // We're in zombies, check clean-up
-                if rs.used_by_count.is_empty() {
-                    // TODO move to logging
-                    println!("Dropping zombie service: {:?}", rs.make_service_ref());
-                    self.zombies.remove(&svc_id);
-                }
+            ServiceRegistry::decrement_use(rs, user_id);
+
+            // We're in zombies, check clean-up
+            if !rs.is_in_use() {
+                rs.log_dropped("Dropping zombie service");
+                self.zombies.remove(&svc_id);
             }
         }
     }
 
-    fn decrement_use(rs: &mut RegisteredService, owner_id: DynamodId) -> Option<u32> {
-        rs.used_by_count.get_mut(&owner_id).map(|cr| {
-            *cr = (*cr) - 1;
-            *cr
-        })
+    // A use unknown to the user's count is one of its leaked handles being dropped.
+    fn decrement_use(rs: &mut RegisteredService, user_id: DynamodId) {
+        match rs.used_by_count.get_mut(&user_id) {
+            Some(cr) => {
+                *cr -= 1;
+                if *cr == 0 {
+                    rs.used_by_count.remove(&user_id);
+                }
+            }
+            None => rs.leaked_count = rs.leaked_count.saturating_sub(1),
+        }
     }
 }
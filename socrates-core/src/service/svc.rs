@@ -79,6 +79,10 @@ impl<T: Service + ?Sized> AsRef<T> for Svc<T> {
 
 impl<T: Service + ?Sized> Drop for Svc<T> {
     fn drop(&mut self) {
+        // Dropping the last use may unload the library holding the service's vtable,
+        // which is needed to drop our weak reference: drop it first.
+        self.service = None;
+
         // Could be none if panic during Svc<dyn Service>::cast
         if let Some(ref svc_manager) = self.svc_manager.upgrade() {
             svc_manager.remove_use(self.service_id, self.user_id);
@@ -1,7 +1,15 @@
 use socrates::common::{EventListener, Listener};
-use socrates::module::Container;
+use socrates::module::{Container, ModuleEvent, PermissionPolicy, TrustPolicy};
 use socrates::service::ServiceEvent;
 use socrates::Result;
+use std::time::Duration;
+
+const PROVIDER_PATH: &str = "examples/target/debug/libexampleprovider.so";
+const CONSUMER_PATH: &str = "examples/target/debug/libexampleconsumer.so";
+
+// Overridden by RUST_LOG, e.g. `RUST_LOG=socrates_core=debug` to follow services and components.
+const DEFAULT_LOG_FILTER: &str = "info";
+
 #[derive(Clone)]
 struct MyListener;
 
@@ -11,33 +19,107 @@ impl EventListener<ServiceEvent> for MyListener {
     }
 }
 
+impl EventListener<ModuleEvent> for MyListener {
+    fn on_event(&self, event: &ModuleEvent) {
+        println!("Received: {:?}", event);
+    }
+}
+
 fn main() -> Result<()> {
-    env_logger::init();
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(DEFAULT_LOG_FILTER))
+        .init();
 
     println!("True knowledge exists in knowing that you know nothing.");
     let mut dmc = Container::new();
 
     let _f = dmc.register_listener(Listener::new(MyListener))?;
+    let _m = dmc.register_module_listener(Listener::new(MyListener))?;
 
-    dmc.install("examples/target/debug/libexampleprovider.so")
-        .expect("couldn't install provider");
-    dmc.install("examples/target/debug/libexampleconsumer.so")
-        .expect("couldn't install consumer");
+    // socrates-launcher [--state <dir>] [--deploy <dir>] [--permissions <policy.json>]
+    //                   [--trusted-digest <hex SHA-256 digest>]...
+    //                   [--trusted-key <hex ed25519 public key>]...
+    let mut state_dir = None;
+    let mut deploy_dir = None;
+    let mut permissions = None;
+    let mut trust_policy = TrustPolicy::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--state" => state_dir = args.next(),
+            "--deploy" => deploy_dir = args.next(),
+            "--permissions" => permissions = args.next(),
+            "--trusted-digest" => {
+                let digest = args.next().ok_or("--trusted-digest needs a digest")?;
+                trust_policy.trust_digest_hex(&digest)?;
+            }
+            "--trusted-key" => {
+                let key = args.next().ok_or("--trusted-key needs a key")?;
+                trust_policy.trust_key_hex(&key)?;
+            }
+            _ => return Err(format!("Unknown argument: {}", arg).into()),
+        }
+    }
 
-    dmc.print_installed_modules();
-    dmc.start(1).expect("couldn't start consumer");
+    dmc.set_trust_policy(trust_policy);
+    if let Some(ref permissions) = permissions {
+        dmc.set_permission_policy(PermissionPolicy::load(std::path::Path::new(permissions))?);
+    }
 
-    dmc.start(0).expect("couldn't start provider");
+    // With a state directory, bring back the modules of the previous launch,
+    // or install the examples on the first one.
+    if let Some(ref state_dir) = state_dir {
+        if dmc.restore(state_dir)?.is_empty() && deploy_dir.is_none() {
+            dmc.install(PROVIDER_PATH)?;
+            dmc.install(CONSUMER_PATH)?;
+            dmc.set_start_level(2)?;
+        }
+    }
+
+    // With a deploy directory, run until interrupted or terminated: modules come and go
+    // with their files. Returning drops the container, which stops the modules in order.
+    if let Some(ref deploy_dir) = deploy_dir {
+        let (shutdown, shutdown_requested) = std::sync::mpsc::channel();
+        ctrlc::set_handler(move || {
+            let _ = shutdown.send(());
+        })
+        .map_err(|e| format!("Couldn't handle shutdown signals: {}", e))?;
+
+        dmc.deploy(deploy_dir, Duration::from_secs(1))?;
+        println!("Deploying modules from {}", deploy_dir);
+        let _ = shutdown_requested.recv();
+        println!("Shutting down");
+        return Ok(());
+    }
+
+    if state_dir.is_some() {
+        dmc.print_installed_modules();
+        return Ok(());
+    }
+
+    // Each example library declares a single module.
+    let provider = dmc
+        .install(PROVIDER_PATH)
+        .expect("couldn't install provider")[0];
+    let consumer = dmc
+        .install(CONSUMER_PATH)
+        .expect("couldn't install consumer")[0];
+
+    dmc.print_installed_modules();
+    // the provider is on the default start level, before the consumer.
+    dmc.set_start_level(2).expect("couldn't start modules");
 
     // a restart!
-    dmc.stop(0).expect("couldn't stop provider");
-    dmc.start(0).expect("couldn't start provider");
+    dmc.stop(provider).expect("couldn't stop provider");
+    dmc.start(provider).expect("couldn't start provider");
 
-    dmc.stop(0).expect("couldn't stop provider");
+    // back to the first level: only the consumer stops.
+    dmc.set_start_level(1).expect("couldn't stop modules");
+    println!("Consumer is {:?}", dmc.get_state(consumer)?);
 
-    dmc.stop(1).expect("couldn't stop provider");
+    dmc.stop_all().expect("couldn't stop modules");
 
-    dmc.uninstall(0).expect("couldn't uninstall provider");
+    dmc.uninstall(provider)
+        .expect("couldn't uninstall provider");
 
     println!("We're done!");
 
@@ -35,10 +35,7 @@ pub fn component(input: TokenStream) -> TokenStream {
         for f in struct_def.fields.iter() {
             if let syn::Type::Path(ref path) = &f.ty {
                 let p = &path.path;
-                println!("base path {:?}", f.ident);
-
                 for seg in p.segments.iter() {
-                    println!("segment {:?}", seg.ident.to_string());
                     if let syn::PathArguments::AngleBracketed(ref type_params) = seg.arguments {
                         for arg in type_params.args.iter() {
                             if let syn::GenericArgument::Type(tpe) = arg {
@@ -46,7 +43,6 @@ pub fn component(input: TokenStream) -> TokenStream {
                                     for bound in trait_obj.bounds.iter() {
                                         if let syn::TypeParamBound::Trait(ref trt) = bound {
                                             for trt_seg in trt.path.segments.iter() {
-                                                println!("trait {:?}", trt_seg.ident.to_string());
                                                 references.push(ReferenceInfo {
                                                     name: f
                                                         .ident
@@ -63,10 +59,6 @@ pub fn component(input: TokenStream) -> TokenStream {
                                             }
                                         }
                                     }
-                                } else if let syn::Type::Path(ref p) = tpe {
-                                    for trt_seg in p.path.segments.iter() {
-                                        println!("path {:?}", trt_seg.ident.to_string());
-                                    }
                                 }
                             }
                         }
@@ -114,7 +106,7 @@ pub fn component(input: TokenStream) -> TokenStream {
 
     let mut quoted_provides = Vec::new();
     for prov in provided.iter() {
-        let prov_name = syn::parse_str::<syn::Expr>(&prov.to_string()).ok();;
+        let prov_name = syn::parse_str::<syn::Expr>(&prov.to_string()).ok();
         quoted_provides.push(quote! {
             socrates::component::definition::Provide {
                 name: socrates::service::Service::get_name::<#prov_name>().to_string(),
@@ -192,14 +184,14 @@ pub fn component(input: TokenStream) -> TokenStream {
             }
 
             fn instantiate(ctx: &socrates::module::Context, references: &socrates::component::ComponentReferences) -> Option<#struct_name> {
-                println!("Instanciating me, {}, unimplemented!", #struct_name_as_string);
+                socrates::log::debug!(component = #struct_name_as_string; "Instanciating me, {}, unimplemented!", #struct_name_as_string);
                 unimplemented!();
             }
 
             fn update(&self, field_id: usize, ctx: &socrates::module::Context,
                     references: &socrates::component::ComponentReferences,
                 ) -> Option<()> {
-                    println!("I'm updated");
+                    socrates::log::debug!(component = #struct_name_as_string, field_id; "I'm updated");
                     Some(())
             }
         }
@@ -207,9 +199,7 @@ pub fn component(input: TokenStream) -> TokenStream {
         #lifecycle_trait
     };
 
-    let r: TokenStream = expanded.into();
-    println!("{}", r.to_string());
-    r
+    expanded.into()
 }
 
 #[proc_macro_attribute]
@@ -242,7 +232,5 @@ pub fn service_trait(_attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     };
 
-    let r: TokenStream = expanded.into();
-    println!("{}", r.to_string());
-    r
+    expanded.into()
 }
@@ -0,0 +1,45 @@
+//! Activators shared by the fixture libraries.
+
+use socrates::module::{Activator, Context};
+use socrates::service::{Service, ServiceRegistration};
+use socrates::Result;
+
+use query_interface::*;
+
+pub struct Thing;
+impl Service for Thing {}
+interfaces!(Thing: dyn Service);
+
+struct Registered(Option<ServiceRegistration>);
+impl Activator for Registered {}
+
+/// Registers a "thing" service.
+pub fn activate_thing(ctx: Context) -> Result<Box<dyn Activator>> {
+    let registration = ctx.register_service(
+        std::any::TypeId::of::<Thing>(),
+        "thing",
+        Default::default(),
+        Box::new(Thing),
+    )?;
+    Ok(Box::new(Registered(Some(registration))))
+}
+
+pub fn activate_nothing(_ctx: Context) -> Result<Box<dyn Activator>> {
+    Ok(Box::new(Registered(None)))
+}
+
+/// Doesn't stop until a `release` file is created in the module's data directory.
+struct Stuck;
+impl Activator for Stuck {
+    fn stop(&mut self, ctx: &Context) -> Result<()> {
+        let release = ctx.data_dir()?.join("release");
+        while !release.exists() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        Ok(())
+    }
+}
+
+pub fn activate_stuck(_ctx: Context) -> Result<Box<dyn Activator>> {
+    Ok(Box::new(Stuck))
+}
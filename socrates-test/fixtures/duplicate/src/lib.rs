@@ -0,0 +1,12 @@
+//! Declares "alpha" twice, it must be refused.
+
+socrates::declare_module! {
+    {
+        manifest: r#"{ "name": "alpha", "version": "1.0.0" }"#,
+        activate: fixture_common::activate_nothing,
+    },
+    {
+        manifest: r#"{ "name": "alpha", "version": "1.0.0" }"#,
+        activate: fixture_common::activate_nothing,
+    },
+}
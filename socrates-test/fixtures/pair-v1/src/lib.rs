@@ -0,0 +1,12 @@
+//! Declares "alpha", providing a "thing", and "beta".
+
+socrates::declare_module! {
+    {
+        manifest: r#"{ "name": "alpha", "version": "1.0.0", "provides": ["thing"] }"#,
+        activate: fixture_common::activate_thing,
+    },
+    {
+        manifest: r#"{ "name": "beta", "version": "1.0.0" }"#,
+        activate: fixture_common::activate_nothing,
+    },
+}
@@ -0,0 +1,16 @@
+//! The next version of pair-v1, it adds "gamma".
+
+socrates::declare_module! {
+    {
+        manifest: r#"{ "name": "alpha", "version": "2.0.0", "provides": ["thing"] }"#,
+        activate: fixture_common::activate_thing,
+    },
+    {
+        manifest: r#"{ "name": "beta", "version": "2.0.0" }"#,
+        activate: fixture_common::activate_nothing,
+    },
+    {
+        manifest: r#"{ "name": "gamma", "version": "2.0.0" }"#,
+        activate: fixture_common::activate_nothing,
+    },
+}
@@ -0,0 +1,6 @@
+//! A single module providing a "thing".
+
+socrates::declare_module! {
+    manifest: r#"{ "name": "solo", "version": "1.0.0", "provides": ["thing"] }"#,
+    activate: fixture_common::activate_thing,
+}
@@ -0,0 +1,6 @@
+//! The next version of solo-v1, the module is renamed.
+
+socrates::declare_module! {
+    manifest: r#"{ "name": "renamed", "version": "2.0.0", "provides": ["thing"] }"#,
+    activate: fixture_common::activate_thing,
+}
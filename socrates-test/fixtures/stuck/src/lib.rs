@@ -0,0 +1,12 @@
+//! Declares "stuck", which doesn't stop until it is released, and "free".
+
+socrates::declare_module! {
+    {
+        manifest: r#"{ "name": "stuck", "version": "1.0.0" }"#,
+        activate: fixture_common::activate_stuck,
+    },
+    {
+        manifest: r#"{ "name": "free", "version": "1.0.0" }"#,
+        activate: fixture_common::activate_nothing,
+    },
+}
@@ -0,0 +1,139 @@
+use crate::support::*;
+
+use socrates::module::*;
+use socrates::Error;
+
+use std::env::consts::DLL_EXTENSION;
+use std::path::Path;
+
+const MANIFEST: &str = r#"{ "name": "archived", "version": "1.0.0" }"#;
+
+fn library(name: &str) -> String {
+    format!("{}.{}", name, DLL_EXTENSION)
+}
+
+/// Builds an archive in memory, entries with a link target are symbolic links.
+fn archive(entries: &[(&str, &[u8], Option<&str>)]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, content, link) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        match link {
+            Some(target) => {
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_size(0);
+                builder.append_link(&mut header, path, target).unwrap();
+            }
+            None => {
+                header.set_size(content.len() as u64);
+                builder.append_data(&mut header, path, *content).unwrap();
+            }
+        }
+    }
+    builder.into_inner().unwrap()
+}
+
+/// Appends an entry `tar::Builder` would refuse to write.
+fn append_raw(bytes: &mut Vec<u8>, path: &str, content: &[u8]) {
+    let mut builder = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_old();
+    header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+    header.set_mode(0o644);
+    header.set_size(content.len() as u64);
+    header.set_cksum();
+    builder.append(&header, content).unwrap();
+    // Drops the end of archive marker, twice a block of zeros.
+    bytes.truncate(bytes.len() - 1024);
+    bytes.extend(builder.into_inner().unwrap());
+}
+
+fn assert_refused(bytes: &[u8], found: usize) {
+    let cache_dir = TestDir::new("archive-refused");
+
+    match ModuleArchive::extract(Path::new("module.smod"), bytes, cache_dir.path()) {
+        Err(Error::InvalidManifest(reason)) => assert!(
+            reason.ends_with(&format!("exactly one library, found {}", found)),
+            "unexpected reason: {}",
+            reason
+        ),
+        Err(e) => panic!("expected InvalidManifest, got {:?}", e),
+        Ok(_) => panic!("expected the archive to be refused"),
+    }
+    // Nothing is left behind.
+    assert_eq!(std::fs::read_dir(cache_dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn archives_are_extracted_with_their_manifest() {
+    let cache_dir = TestDir::new("archive-extracted");
+    let lib = library("module");
+    let bytes = archive(&[
+        (&lib, b"library", None),
+        ("manifest.json", MANIFEST.as_bytes(), None),
+        ("resources/greeting.txt", b"hello", None),
+    ]);
+
+    let extracted =
+        ModuleArchive::extract(Path::new("module.smod"), &bytes, cache_dir.path()).unwrap();
+
+    assert!(extracted.dir.starts_with(cache_dir.path()));
+    assert_eq!(extracted.library, extracted.dir.join(&lib));
+    assert_eq!(extracted.manifest.unwrap().name, "archived");
+    let resource = ModuleArchive::resource_path(&extracted.dir, "greeting.txt").unwrap();
+    assert_eq!(std::fs::read(resource).unwrap(), b"hello");
+}
+
+#[test]
+fn entries_outside_of_the_archive_are_skipped() {
+    let cache_dir = TestDir::new("archive-escaping");
+    let mut bytes = archive(&[(&library("module"), b"library", None)]);
+    append_raw(&mut bytes, "../escaped", b"escaped");
+    let entries = tar::Archive::new(&bytes[..]).entries().unwrap().count();
+    assert_eq!(entries, 2);
+
+    let extracted =
+        ModuleArchive::extract(Path::new("module.smod"), &bytes, cache_dir.path()).unwrap();
+
+    assert!(extracted.dir.starts_with(cache_dir.path()));
+    assert!(!cache_dir.path().join("escaped").exists());
+}
+
+#[test]
+fn archives_must_hold_exactly_one_library() {
+    assert_refused(&archive(&[("manifest.json", MANIFEST.as_bytes(), None)]), 0);
+    assert_refused(
+        &archive(&[
+            (&library("one"), b"library", None),
+            (&library("two"), b"library", None),
+        ]),
+        2,
+    );
+}
+
+#[test]
+fn symbolic_links_to_libraries_are_not_libraries() {
+    assert_refused(
+        &archive(&[
+            ("module.bin", b"library", None),
+            (&library("module"), b"", Some("module.bin")),
+        ]),
+        0,
+    );
+}
+
+#[test]
+fn resource_paths_cannot_leave_the_resources() {
+    let dir = Path::new("extracted");
+
+    assert_eq!(
+        ModuleArchive::resource_path(dir, "images/./logo.png").unwrap(),
+        dir.join("resources").join("images/./logo.png")
+    );
+    for path in &["../manifest.json", "images/../../module.so", "/etc/passwd"] {
+        assert!(
+            ModuleArchive::resource_path(dir, path).is_err(),
+            "{} was accepted",
+            path
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use crate::support::*;
+
+use socrates::module::*;
+use socrates::Error;
+use socrates::Result;
+
+use std::io::Write;
+
+const MODULE: &str = r#"{ "name": "module", "version": "1.0.0" }"#;
+
+/// Counts its starts in a file of its data directory, fails without one.
+fn activate_counting_starts(ctx: Context) -> Result<Box<dyn Activator>> {
+    let mut starts = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(ctx.data_dir()?.join("starts"))?;
+    writeln!(starts, "start")?;
+    activate_nothing(ctx)
+}
+
+#[test]
+fn the_data_dir_is_created_on_demand_and_kept_until_uninstall() {
+    let dir = TestDir::new("data-dir");
+    let container = Container::new();
+    container.set_data_root(dir.to_str());
+    let id = container.install_static(manifest(MODULE), activate_counting_starts);
+    let data_dir = dir.path().join(id.to_string());
+    assert!(!data_dir.exists());
+
+    container.start(id).unwrap();
+    container.stop(id).unwrap();
+    container.start(id).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(data_dir.join("starts")).unwrap(),
+        "start\nstart\n"
+    );
+
+    container.stop(id).unwrap();
+    assert!(data_dir.exists());
+    container.uninstall(id).unwrap();
+    assert!(!data_dir.exists());
+}
+
+#[test]
+fn there_is_no_data_dir_without_a_data_root() {
+    let container = Container::new();
+    let id = container.install_static(manifest(MODULE), activate_counting_starts);
+
+    match container.start(id) {
+        Err(Error::StrError(reason)) => assert!(reason.contains("No data root")),
+        other => panic!("expected the data dir to be missing, got {:?}", other),
+    }
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Failed);
+}
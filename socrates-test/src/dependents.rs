@@ -0,0 +1,67 @@
+use crate::support::*;
+
+use socrates::module::*;
+use socrates::Error;
+
+const PROVIDER: &str = r#"{ "name": "provider", "version": "1.0.0", "provides": ["thing"] }"#;
+const CONSUMER: &str = r#"{ "name": "consumer", "version": "1.0.0" }"#;
+
+fn info(container: &Container, id: DynamodId) -> ModuleInfo {
+    container
+        .modules()
+        .into_iter()
+        .find(|info| info.id == id)
+        .unwrap()
+}
+
+/// Whether `user` uses the service `provider` registered.
+fn uses_service_of(container: &Container, user: DynamodId, provider: DynamodId) -> bool {
+    let registered = info(container, provider).registered[0].core.id;
+    info(container, user)
+        .used
+        .iter()
+        .any(|(svc_ref, _)| svc_ref.core.id == registered)
+}
+
+#[test]
+fn modules_in_use_are_not_uninstalled_if_unused() {
+    let container = Container::new();
+    let provider = container.install_static(manifest(PROVIDER), activate_thing);
+    let consumer = container.install_static(manifest(CONSUMER), activate_thing_user);
+    container.start(provider).unwrap();
+    container.start(consumer).unwrap();
+
+    assert_eq!(container.get_dependents(provider).unwrap(), vec![consumer]);
+    match container.uninstall_if_unused(provider) {
+        Err(Error::ModuleInUse(id, dependents)) => {
+            assert_eq!(id, provider);
+            assert_eq!(dependents, vec![consumer]);
+        }
+        other => panic!("expected ModuleInUse, got {:?}", other),
+    }
+    assert_eq!(container.get_state(provider).unwrap(), DynamodState::Active);
+
+    container.stop(consumer).unwrap();
+    container.uninstall_if_unused(provider).unwrap();
+    assert!(container.get_state(provider).is_err());
+}
+
+#[test]
+fn refresh_restarts_the_users_of_uninstalled_modules() {
+    let container = Container::new();
+    let old_provider = container.install_static(manifest(PROVIDER), activate_thing);
+    let consumer = container.install_static(manifest(CONSUMER), activate_thing_user);
+    container.start(old_provider).unwrap();
+    container.start(consumer).unwrap();
+
+    assert_eq!(container.uninstall(old_provider).unwrap(), vec![consumer]);
+    let new_provider = container.install_static(manifest(PROVIDER), activate_thing);
+    container.start(new_provider).unwrap();
+    assert!(!uses_service_of(&container, consumer, new_provider));
+
+    assert_eq!(container.refresh().unwrap(), vec![consumer]);
+    assert_eq!(container.get_state(consumer).unwrap(), DynamodState::Active);
+    assert!(uses_service_of(&container, consumer, new_provider));
+
+    assert!(container.refresh().unwrap().is_empty());
+}
@@ -0,0 +1,64 @@
+use crate::support::*;
+
+use socrates::common::Listener;
+use socrates::module::*;
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const INTERVAL: Duration = Duration::from_millis(20);
+
+// Replaced in one go, like a deploy tool would.
+fn replace(from: &Path, to: &Path) {
+    let tmp = to.with_extension("tmp");
+    std::fs::copy(from, &tmp).unwrap();
+    std::fs::rename(&tmp, to).unwrap();
+}
+
+fn wait_for_name(container: &Container, name: &str) -> DynamodId {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let found = container
+            .modules()
+            .into_iter()
+            .find(|info| info.manifest.name == name);
+        if let Some(info) = found {
+            return info.id;
+        }
+        assert!(Instant::now() < deadline, "{} was never deployed", name);
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn deployed_files_are_updated_once_even_when_watched() {
+    let dir = TestDir::new("deploy-watched");
+    let deploy_dir = dir.path().join("deploy");
+    std::fs::create_dir(&deploy_dir).unwrap();
+    let lib = deploy_dir.join(format!("solo.{}", std::env::consts::DLL_EXTENSION));
+    replace(&fixture("solo-v1"), &lib);
+
+    let container = Container::new();
+    container
+        .deploy(deploy_dir.to_str().unwrap(), INTERVAL)
+        .unwrap();
+    container.watch(INTERVAL).unwrap();
+    let id = wait_for_name(&container, "solo");
+    wait_for_state(&container, id, DynamodState::Active);
+    let recorder = container
+        .register_module_listener(Listener::new(Recorder::default()))
+        .unwrap();
+
+    replace(&fixture("solo-v2"), &lib);
+
+    assert_eq!(wait_for_name(&container, "renamed"), id);
+    wait_for_state(&container, id, DynamodState::Active);
+    // Leaves the watcher a few intervals to reload it again.
+    std::thread::sleep(INTERVAL * 5);
+    let updates = recorder
+        .take()
+        .into_iter()
+        .filter(|event| *event == ModuleEvent::ModuleUpdated(id))
+        .count();
+    assert_eq!(updates, 1);
+}
@@ -0,0 +1,48 @@
+use crate::support::*;
+
+use socrates::module::*;
+use socrates::Error;
+
+fn named(name: &str) -> Manifest {
+    manifest(&format!(r#"{{ "name": "{}", "version": "1.0.0" }}"#, name))
+}
+
+fn expect_not_found(result: socrates::Result<impl std::fmt::Debug>, id: DynamodId) {
+    match result {
+        Err(Error::ModuleNotFound(missing)) => assert_eq!(missing, id),
+        other => panic!("expected ModuleNotFound, got {:?}", other),
+    }
+}
+
+#[test]
+fn ids_are_stable_and_never_reused() {
+    let container = Container::new();
+    let a = container.install_static(named("a"), activate_nothing);
+    let b = container.install_static(named("b"), activate_nothing);
+    let c = container.install_static(named("c"), activate_nothing);
+
+    container.uninstall(b).unwrap();
+    let d = container.install_static(named("d"), activate_nothing);
+
+    assert!(d != a && d != b && d != c);
+    assert_eq!(container.get_manifest(a).unwrap().name, "a");
+    assert_eq!(container.get_manifest(c).unwrap().name, "c");
+    assert_eq!(container.get_manifest(d).unwrap().name, "d");
+    let mut ids: Vec<DynamodId> = container.modules().iter().map(|m| m.id).collect();
+    ids.sort_unstable();
+    assert_eq!(ids, vec![a, c, d]);
+}
+
+#[test]
+fn unknown_ids_are_reported() {
+    let container = Container::new();
+    let id = container.install_static(named("a"), activate_nothing);
+    container.uninstall(id).unwrap();
+
+    expect_not_found(container.get_state(id), id);
+    expect_not_found(container.get_manifest(id), id);
+    expect_not_found(container.start(id), id);
+    expect_not_found(container.stop(id), id);
+    expect_not_found(container.uninstall(id), id);
+    expect_not_found(container.get_module_start_level(42), 42);
+}
@@ -0,0 +1,51 @@
+use crate::support::*;
+
+use socrates::common::Listener;
+use socrates::module::*;
+
+const LAZY_PROVIDER: &str = r#"{
+    "name": "provider",
+    "version": "1.0.0",
+    "provides": ["thing"],
+    "activation": "lazy"
+}"#;
+const CONSUMER: &str = r#"{ "name": "consumer", "version": "1.0.0" }"#;
+
+#[test]
+fn lazy_modules_are_not_started_with_the_others() {
+    let container = Container::new();
+    let provider = container.install_static(manifest(LAZY_PROVIDER), activate_thing);
+    let other = container.install_static(manifest(CONSUMER), activate_nothing);
+
+    container.start_all().unwrap();
+
+    assert_eq!(
+        container.get_state(provider).unwrap(),
+        DynamodState::Resolved
+    );
+    assert_eq!(container.get_state(other).unwrap(), DynamodState::Active);
+}
+
+#[test]
+fn lazy_modules_start_on_a_lookup_by_name() {
+    let container = Container::new();
+    let provider = container.install_static(manifest(LAZY_PROVIDER), activate_thing);
+    let consumer = container.install_static(manifest(CONSUMER), activate_thing_user);
+    let recorder = container
+        .register_module_listener(Listener::new(Recorder::default()))
+        .unwrap();
+
+    container.start(consumer).unwrap();
+
+    assert_eq!(container.get_state(provider).unwrap(), DynamodState::Active);
+    assert_eq!(container.get_state(consumer).unwrap(), DynamodState::Active);
+    assert_eq!(
+        recorder.take(),
+        vec![
+            ModuleEvent::ModuleStarting(consumer),
+            ModuleEvent::ModuleStarting(provider),
+            ModuleEvent::ModuleStarted(provider),
+            ModuleEvent::ModuleStarted(consumer),
+        ]
+    );
+}
@@ -0,0 +1,110 @@
+use crate::support::*;
+
+use socrates::common::{EventListener, Listener};
+use socrates::module::*;
+use socrates::service::*;
+use socrates::Result;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+const PROVIDER: &str = r#"{ "name": "provider", "version": "1.0.0", "provides": ["thing"] }"#;
+const LEAKY: &str = r#"{ "name": "leaky", "version": "1.0.0", "requires": [{ "name": "thing" }] }"#;
+const OTHER: &str = r#"{ "name": "other", "version": "1.0.0" }"#;
+
+static MODULE_EVENTS: AtomicUsize = AtomicUsize::new(0);
+static SERVICE_EVENTS: AtomicUsize = AtomicUsize::new(0);
+
+struct Counter;
+impl EventListener<ModuleEvent> for Counter {
+    fn on_event(&self, _event: &ModuleEvent) {
+        MODULE_EVENTS.fetch_add(1, Ordering::SeqCst);
+    }
+}
+impl EventListener<ServiceEvent> for Counter {
+    fn on_event(&self, _event: &ServiceEvent) {
+        SERVICE_EVENTS.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Registers a service, gets one and registers listeners, and forgets all of them.
+fn activate_leaky(ctx: Context) -> Result<Box<dyn Activator>> {
+    std::mem::forget(ctx.register_module_listener(Listener::new(Counter))?);
+    std::mem::forget(ctx.register_listener(Listener::new(Counter))?);
+    std::mem::forget(register_thing(&ctx, "leaked")?);
+    std::mem::forget(ctx.get_first_service_by_name("thing").ok_or("no thing")?);
+    Ok(Box::new(Holder::default()))
+}
+
+fn info(container: &Container, id: DynamodId) -> ModuleInfo {
+    container
+        .modules()
+        .into_iter()
+        .find(|info| info.id == id)
+        .unwrap()
+}
+
+#[test]
+fn what_a_stopped_module_leaked_is_reclaimed() {
+    let container = Container::new();
+    let provider = container.install_static(manifest(PROVIDER), activate_thing);
+    let leaky = container.install_static(manifest(LEAKY), activate_leaky);
+    container.start(provider).unwrap();
+    container.start(leaky).unwrap();
+
+    let leaked = info(&container, leaky);
+    assert_eq!(leaked.registered.len(), 1);
+    assert_eq!(leaked.registered[0].name, "leaked");
+    assert_eq!(leaked.used.len(), 1);
+    assert!(MODULE_EVENTS.load(Ordering::SeqCst) > 0);
+    assert!(SERVICE_EVENTS.load(Ordering::SeqCst) > 0);
+
+    container.stop(leaky).unwrap();
+
+    let reclaimed = info(&container, leaky);
+    assert!(reclaimed.registered.is_empty());
+    assert!(reclaimed.used.is_empty());
+    assert_eq!(info(&container, provider).registered[0].name, "thing");
+
+    // The leaked listeners are not called anymore.
+    let module_events = MODULE_EVENTS.load(Ordering::SeqCst);
+    let service_events = SERVICE_EVENTS.load(Ordering::SeqCst);
+    let other = container.install_static(manifest(OTHER), activate_thing);
+    container.start(other).unwrap();
+    container.stop(other).unwrap();
+    assert_eq!(MODULE_EVENTS.load(Ordering::SeqCst), module_events);
+    assert_eq!(SERVICE_EVENTS.load(Ordering::SeqCst), service_events);
+}
+
+static HOARDED: Mutex<Option<Svc>> = Mutex::new(None);
+
+/// Keeps the "thing" service after it stops, without telling the container.
+fn activate_hoarder(ctx: Context) -> Result<Box<dyn Activator>> {
+    *HOARDED.lock().unwrap() = Some(ctx.get_first_service_by_name("thing").ok_or("no thing")?);
+    Ok(Box::new(Holder::default()))
+}
+
+#[test]
+fn a_leaked_service_keeps_its_uninstalled_library_loaded() {
+    let dir = TestDir::new("leaked-service");
+    let container = Container::new();
+    let provider = container
+        .install(&copy_fixture(&dir, "solo-v1", "solo.so"))
+        .unwrap()[0];
+    let hoarder = container.install_static(manifest(OTHER), activate_hoarder);
+    container.start(provider).unwrap();
+    container.start(hoarder).unwrap();
+
+    container.stop(hoarder).unwrap();
+    assert!(info(&container, hoarder).used.is_empty());
+    container.uninstall(provider).unwrap();
+
+    let zombies = container.get_zombies();
+    assert_eq!(zombies.len(), 1);
+    assert_eq!(zombies[0].id, provider);
+    assert!(zombies[0].held_by.is_empty());
+
+    // Dropping the handle needs the library's code, it is only unloaded afterwards.
+    *HOARDED.lock().unwrap() = None;
+    assert!(container.get_zombies().is_empty());
+}
@@ -52,8 +52,8 @@ mod tests {
             name: "GreetPrinter".into(),
             references: vec![Reference {
                 name: "Greeter".into(),
-                svc_name: <Greeter as Named>::type_name().into(),
-                svc_query: ServiceQuery::by_type_id(Service::type_id::<Greeter>()),
+                svc_name: <dyn Greeter as Named>::type_name().into(),
+                svc_query: ServiceQuery::by_type_id(service_type_id::<dyn Greeter>()),
                 options: Default::default(),
             }],
             ..Default::default()
@@ -64,3 +64,42 @@ mod tests {
     }
 
 }
+
+#[cfg(test)]
+mod archive;
+#[cfg(test)]
+mod data;
+#[cfg(test)]
+mod dependents;
+#[cfg(test)]
+mod deploy;
+#[cfg(test)]
+mod ids;
+#[cfg(test)]
+mod lazy;
+#[cfg(test)]
+mod leaks;
+#[cfg(test)]
+mod libraries;
+#[cfg(test)]
+mod lifecycle;
+#[cfg(test)]
+mod panics;
+#[cfg(test)]
+mod permissions;
+#[cfg(test)]
+mod persist;
+#[cfg(test)]
+mod registry;
+#[cfg(test)]
+mod resolution;
+#[cfg(test)]
+mod start_levels;
+#[cfg(test)]
+mod support;
+#[cfg(test)]
+mod update;
+#[cfg(test)]
+mod verify;
+#[cfg(test)]
+mod zombies;
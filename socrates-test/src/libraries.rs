@@ -0,0 +1,84 @@
+use crate::support::*;
+
+use socrates::module::*;
+use socrates::Error;
+
+fn names(container: &Container) -> Vec<(DynamodId, String)> {
+    let mut names: Vec<(DynamodId, String)> = container
+        .modules()
+        .into_iter()
+        .map(|info| (info.id, info.manifest.name))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn modules_of_a_library_share_it() {
+    let dir = TestDir::new("shared-library");
+    let container = Container::new();
+    let path = copy_fixture(&dir, "pair-v1", "pair.so");
+
+    let ids = container.install(&path).unwrap();
+    assert_eq!(
+        names(&container),
+        vec![(ids[0], "alpha".to_owned()), (ids[1], "beta".to_owned())]
+    );
+    assert!(container.modules().iter().all(|info| info.path == path));
+
+    // Uninstalling one of them leaves the library loaded for the other.
+    container.uninstall(ids[1]).unwrap();
+    container.start(ids[0]).unwrap();
+    assert_eq!(container.modules()[0].registered[0].name, "thing");
+}
+
+#[test]
+fn libraries_declaring_a_module_twice_are_refused() {
+    let dir = TestDir::new("duplicate");
+    let container = Container::new();
+    let path = copy_fixture(&dir, "duplicate", "duplicate.so");
+
+    match container.install(&path) {
+        Err(Error::InvalidManifest(reason)) => assert!(reason.contains("alpha twice")),
+        other => panic!("expected InvalidManifest, got {:?}", other),
+    }
+    assert!(container.modules().is_empty());
+}
+
+#[test]
+fn update_swaps_every_sibling_and_installs_new_modules() {
+    let dir = TestDir::new("update-siblings");
+    let container = Container::new();
+    let v1 = copy_fixture(&dir, "pair-v1", "pair-v1.so");
+    let v2 = copy_fixture(&dir, "pair-v2", "pair-v2.so");
+
+    let ids = container.install(&v1).unwrap();
+    container.update(ids[1], &v2).unwrap();
+
+    let modules = names(&container);
+    assert_eq!(modules.len(), 3);
+    assert_eq!(modules[0], (ids[0], "alpha".to_owned()));
+    assert_eq!(modules[1], (ids[1], "beta".to_owned()));
+    assert_eq!(modules[2].1, "gamma");
+    assert!(container
+        .modules()
+        .iter()
+        .all(|info| info.path == v2 && info.manifest.version.major == 2));
+}
+
+#[test]
+fn modules_uninstalled_from_the_old_library_stay_uninstalled() {
+    let dir = TestDir::new("update-uninstalled");
+    let container = Container::new();
+    let v1 = copy_fixture(&dir, "pair-v1", "pair-v1.so");
+    let v2 = copy_fixture(&dir, "pair-v2", "pair-v2.so");
+
+    let ids = container.install(&v1).unwrap();
+    container.uninstall(ids[1]).unwrap();
+    container.update(ids[0], &v2).unwrap();
+
+    let modules = names(&container);
+    assert_eq!(modules.len(), 2);
+    assert_eq!(modules[0], (ids[0], "alpha".to_owned()));
+    assert_eq!(modules[1].1, "gamma");
+}
@@ -0,0 +1,192 @@
+use crate::support::*;
+
+use socrates::common::Listener;
+use socrates::module::*;
+use socrates::Error;
+use socrates::Result;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+const MODULE: &str = r#"{ "name": "module", "version": "1.0.0" }"#;
+
+static HUNG_STOP_RELEASED: AtomicBool = AtomicBool::new(false);
+
+struct HungStop;
+impl Activator for HungStop {
+    fn stop(&mut self, _ctx: &Context) -> Result<()> {
+        while !HUNG_STOP_RELEASED.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        Ok(())
+    }
+}
+
+fn activate_hung_stop(_ctx: Context) -> Result<Box<dyn Activator>> {
+    Ok(Box::new(HungStop))
+}
+
+#[test]
+fn abandoned_module_cannot_restart_until_its_stop_returns() {
+    let container = Container::new();
+    container.set_stop_timeout(Some(Duration::from_millis(50)));
+    let id = container.install_static(manifest(MODULE), activate_hung_stop);
+    container.start(id).unwrap();
+
+    match container.stop(id) {
+        Err(Error::StopTimeout(stopped)) => assert_eq!(stopped, id),
+        other => panic!("expected StopTimeout, got {:?}", other),
+    }
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Stopping);
+    match container.start(id) {
+        Err(Error::InvalidModuleState(_, DynamodState::Stopping)) => (),
+        other => panic!("expected InvalidModuleState, got {:?}", other),
+    }
+
+    HUNG_STOP_RELEASED.store(true, Ordering::SeqCst);
+    wait_for_state(&container, id, DynamodState::Failed);
+
+    container.start(id).unwrap();
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Active);
+}
+
+fn activate_failing(_ctx: Context) -> Result<Box<dyn Activator>> {
+    Err("activation failed".into())
+}
+
+#[test]
+fn lifecycle_events_follow_the_state_transitions() {
+    let container = Container::new();
+    let recorder = container
+        .register_module_listener(Listener::new(Recorder::default()))
+        .unwrap();
+
+    let id = container.install_static(manifest(MODULE), activate_nothing);
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Resolved);
+    assert_eq!(
+        recorder.take(),
+        vec![
+            ModuleEvent::ModuleInstalled(id),
+            ModuleEvent::ModuleResolved(id)
+        ]
+    );
+
+    container.start(id).unwrap();
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Active);
+    assert_eq!(
+        recorder.take(),
+        vec![
+            ModuleEvent::ModuleStarting(id),
+            ModuleEvent::ModuleStarted(id)
+        ]
+    );
+
+    container.stop(id).unwrap();
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Resolved);
+    assert_eq!(
+        recorder.take(),
+        vec![
+            ModuleEvent::ModuleStopping(id),
+            ModuleEvent::ModuleStopped(id)
+        ]
+    );
+
+    container.uninstall(id).unwrap();
+    assert_eq!(recorder.take(), vec![ModuleEvent::ModuleUninstalled(id)]);
+}
+
+#[test]
+fn failed_activation_leaves_the_module_failed_and_restartable() {
+    let container = Container::new();
+    let id = container.install_static(manifest(MODULE), activate_failing);
+    let recorder = container
+        .register_module_listener(Listener::new(Recorder::default()))
+        .unwrap();
+
+    assert!(container.start(id).is_err());
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Failed);
+    assert_eq!(
+        recorder.take(),
+        vec![
+            ModuleEvent::ModuleStarting(id),
+            ModuleEvent::ModuleFailed(id)
+        ]
+    );
+
+    assert!(container.start(id).is_err());
+    assert_eq!(
+        recorder.take(),
+        vec![
+            ModuleEvent::ModuleResolved(id),
+            ModuleEvent::ModuleStarting(id),
+            ModuleEvent::ModuleFailed(id)
+        ]
+    );
+}
+
+#[test]
+fn invalid_transitions_are_refused() {
+    let container = Container::new();
+    let id = container.install_static(manifest(MODULE), activate_nothing);
+
+    match container.stop(id) {
+        Err(Error::InvalidModuleState(stopped, DynamodState::Resolved)) => assert_eq!(stopped, id),
+        other => panic!("expected InvalidModuleState, got {:?}", other),
+    }
+
+    container.start(id).unwrap();
+    match container.start(id) {
+        Err(Error::InvalidModuleState(started, DynamodState::Active)) => assert_eq!(started, id),
+        other => panic!("expected InvalidModuleState, got {:?}", other),
+    }
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Active);
+}
+
+fn activate_giving_up(ctx: Context) -> Result<Box<dyn Activator>> {
+    ctx.fail("gave up".to_owned());
+    activate_nothing(ctx)
+}
+
+#[test]
+fn a_module_failing_itself_is_not_reported_as_a_panic() {
+    let container = Container::new();
+    let id = container.install_static(manifest(MODULE), activate_giving_up);
+
+    match container.start(id) {
+        Err(Error::ModuleFailed(failed, reason)) => {
+            assert_eq!(failed, id);
+            assert_eq!(reason, "gave up");
+        }
+        other => panic!("expected ModuleFailed, got {:?}", other),
+    }
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Failed);
+}
+
+struct GivingUpStop;
+impl Activator for GivingUpStop {
+    fn stop(&mut self, ctx: &Context) -> Result<()> {
+        ctx.fail("gave up".to_owned());
+        Ok(())
+    }
+}
+
+fn activate_giving_up_stop(_ctx: Context) -> Result<Box<dyn Activator>> {
+    Ok(Box::new(GivingUpStop))
+}
+
+#[test]
+fn a_module_failing_while_it_stops_ends_up_failed() {
+    let container = Container::new();
+    let id = container.install_static(manifest(MODULE), activate_giving_up_stop);
+    container.start(id).unwrap();
+
+    match container.stop(id) {
+        Err(Error::ModuleFailed(failed, reason)) => {
+            assert_eq!(failed, id);
+            assert_eq!(reason, "gave up");
+        }
+        other => panic!("expected ModuleFailed, got {:?}", other),
+    }
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Failed);
+}
@@ -0,0 +1,192 @@
+use crate::support::*;
+
+use socrates::common::{EventListener, Listener};
+use socrates::component::*;
+use socrates::module::Context;
+use socrates::module::*;
+use socrates::service::*;
+use socrates::Error;
+use socrates::Result;
+
+const MODULE: &str = r#"{ "name": "module", "version": "1.0.0" }"#;
+const PROVIDER: &str = r#"{ "name": "provider", "version": "1.0.0" }"#;
+
+fn failure_of(container: &Container, id: DynamodId) -> Option<String> {
+    container
+        .modules()
+        .into_iter()
+        .find(|info| info.id == id)
+        .and_then(|info| info.failure)
+}
+
+struct Panicking;
+impl EventListener<ServiceEvent> for Panicking {
+    fn on_event(&self, _event: &ServiceEvent) {
+        panic!("boom");
+    }
+}
+impl EventListener<ModuleEvent> for Panicking {
+    fn on_event(&self, _event: &ModuleEvent) {
+        panic!("boom");
+    }
+}
+
+#[derive(Default)]
+struct Listening {
+    _service: Option<Listener<Panicking, ServiceEvent>>,
+    _module: Option<Listener<Panicking, ModuleEvent>>,
+}
+impl Activator for Listening {}
+
+fn activate_service_listener(ctx: Context) -> Result<Box<dyn Activator>> {
+    Ok(Box::new(Listening {
+        _service: Some(ctx.register_listener(Listener::new(Panicking))?),
+        ..Default::default()
+    }))
+}
+
+fn activate_module_listener(ctx: Context) -> Result<Box<dyn Activator>> {
+    Ok(Box::new(Listening {
+        _module: Some(ctx.register_module_listener(Listener::new(Panicking))?),
+        ..Default::default()
+    }))
+}
+
+#[test]
+fn a_panicking_service_listener_fails_its_module() {
+    let container = Container::new();
+    let id = container.install_static(manifest(MODULE), activate_service_listener);
+    let provider = container.install_static(manifest(PROVIDER), activate_thing);
+    container.start(id).unwrap();
+
+    container.start(provider).unwrap();
+
+    assert_eq!(container.get_state(provider).unwrap(), DynamodState::Active);
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Failed);
+    assert_eq!(
+        failure_of(&container, id).as_deref(),
+        Some("Panic in a listener: boom")
+    );
+}
+
+#[test]
+fn a_panicking_module_listener_fails_its_module() {
+    let container = Container::new();
+    let id = container.install_static(manifest(MODULE), activate_module_listener);
+    let other = container.install_static(manifest(PROVIDER), activate_nothing);
+    container.start(id).unwrap();
+
+    container.start(other).unwrap();
+
+    assert_eq!(container.get_state(other).unwrap(), DynamodState::Active);
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Failed);
+    assert_eq!(
+        failure_of(&container, id).as_deref(),
+        Some("Panic in a listener: boom")
+    );
+}
+
+struct PanickingStop;
+impl Activator for PanickingStop {
+    fn stop(&mut self, _ctx: &Context) -> Result<()> {
+        panic!("boom");
+    }
+}
+
+fn activate_panicking_stop(_ctx: Context) -> Result<Box<dyn Activator>> {
+    Ok(Box::new(PanickingStop))
+}
+
+struct PanickingDrop;
+impl Activator for PanickingDrop {}
+impl Drop for PanickingDrop {
+    fn drop(&mut self) {
+        panic!("boom");
+    }
+}
+
+fn activate_panicking_drop(_ctx: Context) -> Result<Box<dyn Activator>> {
+    Ok(Box::new(PanickingDrop))
+}
+
+fn assert_stop_panics(activate: ActivateFn) {
+    let container = Container::new();
+    let id = container.install_static(manifest(MODULE), activate);
+    container.start(id).unwrap();
+
+    match container.stop(id) {
+        Err(Error::Panic(message)) => assert_eq!(message, "Panic in deactivate: boom"),
+        other => panic!("expected Panic, got {:?}", other),
+    }
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Failed);
+    assert_eq!(
+        failure_of(&container, id).as_deref(),
+        Some("Panic in deactivate: boom")
+    );
+
+    // Its next stop may panic again, but it starts fine.
+    container.start(id).unwrap();
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Active);
+}
+
+#[test]
+fn a_panicking_stop_fails_the_module() {
+    assert_stop_panics(activate_panicking_stop);
+}
+
+#[test]
+fn a_panicking_activator_drop_fails_the_module() {
+    assert_stop_panics(activate_panicking_drop);
+}
+
+struct Exploding;
+impl Lifecycle for Exploding {}
+impl Component for Exploding {
+    fn get_definition() -> ComponentDefinition {
+        ComponentDefinition {
+            name: "exploding".to_owned(),
+            provides: Vec::new(),
+            references: vec![Reference {
+                name: "thing".to_owned(),
+                svc_name: "thing".to_owned(),
+                svc_query: ServiceQuery::by_name("thing".to_owned()),
+                options: Default::default(),
+            }],
+        }
+    }
+
+    fn instantiate(_context: &Context, _references: &ComponentReferences) -> Option<Self> {
+        panic!("boom");
+    }
+
+    fn update(
+        &self,
+        _field_id: usize,
+        _context: &Context,
+        _references: &ComponentReferences,
+    ) -> Option<()> {
+        Some(())
+    }
+}
+
+fn activate_components(ctx: Context) -> Result<Box<dyn Activator>> {
+    let manager = ComponentManager::new().add_component::<Exploding>();
+    Ok(ComponentManagerHandler::start(&ctx, manager)?.boxed())
+}
+
+#[test]
+fn a_panicking_component_instantiation_fails_its_module() {
+    let container = Container::new();
+    let id = container.install_static(manifest(MODULE), activate_components);
+    let provider = container.install_static(manifest(PROVIDER), activate_thing);
+    container.start(id).unwrap();
+
+    container.start(provider).unwrap();
+
+    assert_eq!(container.get_state(provider).unwrap(), DynamodState::Active);
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Failed);
+    assert_eq!(
+        failure_of(&container, id).as_deref(),
+        Some("Panic instantiating component exploding: boom")
+    );
+}
@@ -0,0 +1,90 @@
+use crate::support::*;
+
+use socrates::module::*;
+use socrates::Error;
+
+const PROVIDER: &str = r#"{ "name": "provider", "version": "1.0.0" }"#;
+
+fn deny_register() -> ModulePermissions {
+    ModulePermissions {
+        register: Some(Vec::new()),
+        ..Default::default()
+    }
+}
+
+fn assert_register_denied(result: socrates::Result<()>, id: DynamodId) {
+    match result {
+        Err(Error::PermissionDenied(denied, Permission::Register, svc_name)) => {
+            assert_eq!(denied, id);
+            assert_eq!(svc_name, "thing");
+        }
+        other => panic!("expected PermissionDenied, got {:?}", other),
+    }
+}
+
+#[test]
+fn manifest_permissions_deny_registering() {
+    let container = Container::new();
+    let provider = container.install_static(
+        manifest(r#"{ "name": "provider", "version": "1.0.0", "permissions": { "register": ["other"] } }"#),
+        activate_thing,
+    );
+
+    assert_register_denied(container.start(provider), provider);
+    assert_eq!(container.get_state(provider).unwrap(), DynamodState::Failed);
+}
+
+#[test]
+fn policy_entries_apply_to_the_module_path() {
+    let container = Container::new();
+    let mut policy = PermissionPolicy {
+        default: Some(deny_register()),
+        ..Default::default()
+    };
+    policy
+        .paths
+        .insert("static:trusted".to_owned(), ModulePermissions::default());
+    container.set_permission_policy(policy);
+
+    let trusted = container.install_static(
+        manifest(r#"{ "name": "trusted", "version": "1.0.0" }"#),
+        activate_thing,
+    );
+    let provider = container.install_static(manifest(PROVIDER), activate_thing);
+
+    container.start(trusted).unwrap();
+    assert_register_denied(container.start(provider), provider);
+}
+
+#[test]
+fn policy_entries_are_not_matched_by_module_name() {
+    let container = Container::new();
+    let mut policy = PermissionPolicy::default();
+    policy.paths.insert("provider".to_owned(), deny_register());
+    container.set_permission_policy(policy);
+
+    let provider = container.install_static(manifest(PROVIDER), activate_thing);
+
+    container.start(provider).unwrap();
+}
+
+#[test]
+fn services_a_module_may_not_get_are_hidden() {
+    let container = Container::new();
+    let provider = container.install_static(manifest(PROVIDER), activate_thing);
+    let consumer = container.install_static(
+        manifest(
+            r#"{ "name": "consumer", "version": "1.0.0", "permissions": { "get": ["other"] } }"#,
+        ),
+        activate_thing_user,
+    );
+    container.start(provider).unwrap();
+
+    match container.start(consumer) {
+        Err(Error::StrError(reason)) => assert_eq!(reason, "no thing"),
+        other => panic!(
+            "expected the consumer not to find the thing, got {:?}",
+            other
+        ),
+    }
+}
@@ -0,0 +1,64 @@
+use crate::support::*;
+
+use socrates::module::*;
+
+const STATIC: &str = r#"{ "name": "static", "version": "1.0.0" }"#;
+
+#[test]
+fn static_modules_are_not_saved() {
+    let state_dir = TestDir::new("static-not-saved");
+    let container = Container::new();
+    container.restore(state_dir.to_str()).unwrap();
+
+    let id = container.install_static(manifest(STATIC), activate_nothing);
+    container.start(id).unwrap();
+
+    let state = ContainerState::load(state_dir.path()).unwrap().unwrap();
+    assert!(id >= FIRST_STATIC_ID);
+    assert!(state.modules.is_empty());
+}
+
+#[test]
+fn static_modules_can_be_installed_before_a_restore() {
+    let state_dir = TestDir::new("static-before-restore");
+
+    // First launch: restore, then install the static modules.
+    {
+        let container = Container::new();
+        assert!(container.restore(state_dir.to_str()).unwrap().is_empty());
+        let id = container.install_static(manifest(STATIC), activate_nothing);
+        container.start(id).unwrap();
+    }
+
+    // Next launch: the other way around.
+    let container = Container::new();
+    let id = container.install_static(manifest(STATIC), activate_nothing);
+    container.start(id).unwrap();
+
+    assert!(container.restore(state_dir.to_str()).unwrap().is_empty());
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Active);
+    assert_eq!(container.modules().len(), 1);
+}
+
+#[test]
+fn static_modules_installed_before_a_restore_keep_off_saved_ids() {
+    let state_dir = TestDir::new("static-saved-ids");
+    let lib = copy_fixture(&state_dir, "solo-v1", "solo-v1.so");
+
+    let saved = {
+        let container = Container::new();
+        container.restore(state_dir.to_str()).unwrap();
+        let id = container.install(&lib).unwrap()[0];
+        container.start(id).unwrap();
+        id
+    };
+
+    let container = Container::new();
+    let id = container.install_static(manifest(STATIC), activate_nothing);
+    container.start(id).unwrap();
+
+    assert_eq!(container.restore(state_dir.to_str()).unwrap(), vec![saved]);
+    assert_ne!(id, saved);
+    assert_eq!(container.get_manifest(saved).unwrap().name, "solo");
+    assert_eq!(container.get_state(saved).unwrap(), DynamodState::Active);
+}
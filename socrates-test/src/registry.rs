@@ -0,0 +1,58 @@
+use crate::support::Thing;
+
+use socrates::service::*;
+
+use std::any::TypeId;
+use std::sync::Arc;
+
+fn register(registry: &mut ServiceRegistry, svc_name: &str) -> ServiceId {
+    registry
+        .register_service(
+            TypeId::of::<Thing>(),
+            svc_name,
+            Arc::new(Thing),
+            Default::default(),
+            1,
+            None,
+        )
+        .core
+        .id
+}
+
+#[test]
+fn unregister_keeps_services_with_the_same_name_and_type() {
+    let mut registry = ServiceRegistry::new();
+    let first = register(&mut registry, "thing");
+    let second = register(&mut registry, "thing");
+
+    registry.unregister_service(first);
+
+    let by_name: Vec<ServiceId> = registry.get_services_id_by_name("thing").collect();
+    assert_eq!(by_name, vec![second]);
+    let by_type_id: Vec<ServiceId> = registry
+        .get_services_id_by_type_id(TypeId::of::<Thing>())
+        .collect();
+    assert_eq!(by_type_id, vec![second]);
+
+    registry.unregister_service(second);
+
+    assert_eq!(registry.get_services_id_by_name("thing").count(), 0);
+    assert_eq!(
+        registry
+            .get_services_id_by_type_id(TypeId::of::<Thing>())
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn released_services_can_still_be_looked_up() {
+    let mut registry = ServiceRegistry::new();
+    let svc_id = register(&mut registry, "thing");
+
+    assert!(registry.get_service_object(svc_id, 2).is_some());
+    registry.remove_use(svc_id, 2);
+
+    let by_name: Vec<ServiceId> = registry.get_services_id_by_name("thing").collect();
+    assert_eq!(by_name, vec![svc_id]);
+}
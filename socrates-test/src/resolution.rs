@@ -0,0 +1,102 @@
+use crate::support::*;
+
+use socrates::common::Listener;
+use socrates::module::*;
+use socrates::Error;
+
+const CONSUMER: &str = r#"{
+    "name": "consumer",
+    "version": "1.0.0",
+    "requires": [{ "name": "thing" }, { "name": "extra", "optional": true }]
+}"#;
+const PROVIDER: &str = r#"{ "name": "provider", "version": "1.0.0", "provides": ["thing"] }"#;
+
+#[test]
+fn unresolved_module_reports_what_is_missing_at_install() {
+    let container = Container::new();
+    let recorder = container
+        .register_module_listener(Listener::new(Recorder::default()))
+        .unwrap();
+
+    let consumer = container.install_static(manifest(CONSUMER), activate_thing_user);
+
+    assert_eq!(
+        recorder.take(),
+        vec![
+            ModuleEvent::ModuleInstalled(consumer),
+            ModuleEvent::ModuleUnresolved(consumer, vec!["thing".to_owned()]),
+        ]
+    );
+    assert_eq!(
+        container.get_state(consumer).unwrap(),
+        DynamodState::Installed
+    );
+    match container.start(consumer) {
+        Err(Error::Unresolved(id, missing)) => {
+            assert_eq!(id, consumer);
+            assert_eq!(missing, vec!["thing".to_owned()]);
+        }
+        other => panic!("expected Unresolved, got {:?}", other),
+    }
+}
+
+#[test]
+fn installing_a_provider_resolves_its_consumers() {
+    let container = Container::new();
+    let consumer = container.install_static(manifest(CONSUMER), activate_thing_user);
+    let recorder = container
+        .register_module_listener(Listener::new(Recorder::default()))
+        .unwrap();
+
+    let provider = container.install_static(manifest(PROVIDER), activate_thing);
+
+    assert_eq!(
+        recorder.take(),
+        vec![
+            ModuleEvent::ModuleInstalled(provider),
+            ModuleEvent::ModuleResolved(consumer),
+            ModuleEvent::ModuleResolved(provider),
+        ]
+    );
+
+    container.start(provider).unwrap();
+    container.start(consumer).unwrap();
+    assert_eq!(container.get_state(consumer).unwrap(), DynamodState::Active);
+}
+
+#[test]
+fn host_services_resolve_modules() {
+    let container = Container::new();
+    let consumer = container.install_static(manifest(CONSUMER), activate_nothing);
+
+    container.declare_host_service("thing");
+
+    assert_eq!(
+        container.get_state(consumer).unwrap(),
+        DynamodState::Resolved
+    );
+}
+
+#[test]
+fn uninstalling_a_provider_unresolves_its_consumers() {
+    let container = Container::new();
+    let provider = container.install_static(manifest(PROVIDER), activate_thing);
+    let consumer = container.install_static(manifest(CONSUMER), activate_nothing);
+    let recorder = container
+        .register_module_listener(Listener::new(Recorder::default()))
+        .unwrap();
+
+    container.uninstall(provider).unwrap();
+
+    assert_eq!(
+        recorder.take(),
+        vec![
+            ModuleEvent::ModuleUninstalled(provider),
+            ModuleEvent::ModuleUnresolved(consumer, vec!["thing".to_owned()]),
+        ]
+    );
+    assert_eq!(
+        container.get_state(consumer).unwrap(),
+        DynamodState::Installed
+    );
+}
@@ -0,0 +1,70 @@
+use crate::support::*;
+
+use socrates::common::Listener;
+use socrates::module::*;
+
+fn at_level(name: &str, start_level: StartLevel) -> Manifest {
+    manifest(&format!(
+        r#"{{ "name": "{}", "version": "1.0.0", "start_level": {} }}"#,
+        name, start_level
+    ))
+}
+
+fn started(events: Vec<ModuleEvent>) -> Vec<DynamodId> {
+    events
+        .into_iter()
+        .filter_map(|event| match event {
+            ModuleEvent::ModuleStarted(id) => Some(id),
+            _ => None,
+        })
+        .collect()
+}
+
+fn stopped(events: Vec<ModuleEvent>) -> Vec<DynamodId> {
+    events
+        .into_iter()
+        .filter_map(|event| match event {
+            ModuleEvent::ModuleStopped(id) => Some(id),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn modules_start_by_level_and_stop_in_reverse() {
+    let container = Container::new();
+    let third = container.install_static(at_level("third", 3), activate_nothing);
+    let first = container.install_static(at_level("first", 1), activate_nothing);
+    let second = container.install_static(at_level("second", 2), activate_nothing);
+    let recorder = container
+        .register_module_listener(Listener::new(Recorder::default()))
+        .unwrap();
+
+    container.start_all().unwrap();
+    assert_eq!(started(recorder.take()), vec![first, second, third]);
+
+    container.stop_all().unwrap();
+    assert_eq!(stopped(recorder.take()), vec![third, second, first]);
+}
+
+#[test]
+fn changing_the_start_level_starts_and_stops_modules_in_order() {
+    let container = Container::new();
+    let third = container.install_static(at_level("third", 3), activate_nothing);
+    let first = container.install_static(at_level("first", 1), activate_nothing);
+    let second = container.install_static(at_level("second", 2), activate_nothing);
+    let recorder = container
+        .register_module_listener(Listener::new(Recorder::default()))
+        .unwrap();
+
+    container.set_start_level(3).unwrap();
+    assert_eq!(started(recorder.take()), vec![first, second, third]);
+
+    container.set_start_level(1).unwrap();
+    assert_eq!(stopped(recorder.take()), vec![third, second]);
+    assert_eq!(container.get_state(first).unwrap(), DynamodState::Active);
+
+    container.set_start_level(2).unwrap();
+    assert_eq!(started(recorder.take()), vec![second]);
+    assert_eq!(container.get_state(third).unwrap(), DynamodState::Resolved);
+}
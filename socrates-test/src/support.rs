@@ -0,0 +1,151 @@
+//! Helpers for the tests using static modules.
+
+use socrates::common::EventListener;
+use socrates::module::*;
+use socrates::service::*;
+use socrates::Result;
+
+use query_interface::*;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, Once};
+use std::time::{Duration, Instant};
+
+/// Records the module events, in the order they are fired.
+#[derive(Default)]
+pub struct Recorder {
+    events: Mutex<Vec<ModuleEvent>>,
+}
+
+impl Recorder {
+    /// The events received since the last call.
+    pub fn take(&self) -> Vec<ModuleEvent> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+}
+
+impl EventListener<ModuleEvent> for Recorder {
+    fn on_event(&self, event: &ModuleEvent) {
+        self.events.lock().unwrap().push(event.clone());
+    }
+}
+
+pub fn manifest(json: &str) -> Manifest {
+    Manifest::from_json(json).expect("invalid test manifest")
+}
+
+pub struct Thing;
+impl Service for Thing {}
+interfaces!(Thing: dyn Service);
+
+/// Holds whatever the module registered or got while it is active.
+#[derive(Default)]
+pub struct Holder {
+    pub _registrations: Vec<ServiceRegistration>,
+    pub _services: Vec<Svc>,
+}
+impl Activator for Holder {}
+
+pub fn register_thing(ctx: &Context, svc_name: &str) -> Result<ServiceRegistration> {
+    ctx.register_service(
+        std::any::TypeId::of::<Thing>(),
+        svc_name,
+        Default::default(),
+        Box::new(Thing),
+    )
+}
+
+pub fn activate_nothing(_ctx: Context) -> Result<Box<dyn Activator>> {
+    Ok(Box::new(Holder::default()))
+}
+
+/// Registers a "thing" service.
+pub fn activate_thing(ctx: Context) -> Result<Box<dyn Activator>> {
+    Ok(Box::new(Holder {
+        _registrations: vec![register_thing(&ctx, "thing")?],
+        _services: Vec::new(),
+    }))
+}
+
+/// Gets the "thing" service, fails without it.
+pub fn activate_thing_user(ctx: Context) -> Result<Box<dyn Activator>> {
+    let thing = ctx.get_first_service_by_name("thing").ok_or("no thing")?;
+    Ok(Box::new(Holder {
+        _registrations: Vec::new(),
+        _services: vec![thing],
+    }))
+}
+
+/// An empty directory for the test in the temporary directory, removed when dropped.
+pub struct TestDir(std::path::PathBuf);
+
+impl TestDir {
+    pub fn new(name: &str) -> TestDir {
+        let dir =
+            std::env::temp_dir().join(format!("socrates-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        TestDir(dir)
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.0
+    }
+
+    pub fn to_str(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The library of the fixture crate `name`, from `fixtures/`. They are all built on first use.
+pub fn fixture(name: &str) -> PathBuf {
+    static BUILD: Once = Once::new();
+
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    let target_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/fixtures");
+    BUILD.call_once(|| {
+        let status = Command::new(env!("CARGO"))
+            .args(["build", "--quiet"])
+            .arg("--manifest-path")
+            .arg(fixtures.join("Cargo.toml"))
+            .arg("--target-dir")
+            .arg(&target_dir)
+            .status()
+            .expect("couldn't run cargo");
+        assert!(status.success(), "couldn't build the fixtures");
+    });
+
+    target_dir.join("debug").join(format!(
+        "{}{}{}",
+        std::env::consts::DLL_PREFIX,
+        name.replace('-', "_"),
+        std::env::consts::DLL_SUFFIX
+    ))
+}
+
+/// Copies the library of the fixture crate `name` to `file_name` in `dir`, returns its path.
+pub fn copy_fixture(dir: &TestDir, name: &str, file_name: &str) -> String {
+    let path = dir.path().join(file_name);
+    std::fs::copy(fixture(name), &path).unwrap();
+    path.to_str().unwrap().to_owned()
+}
+
+/// Waits for a module to get `state`, e.g. after its stop thread was abandoned.
+pub fn wait_for_state(container: &Container, id: DynamodId, state: DynamodState) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while container.get_state(id).unwrap() != state {
+        assert!(
+            Instant::now() < deadline,
+            "module #{} never got {:?}",
+            id,
+            state
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,114 @@
+use crate::support::*;
+
+use socrates::common::Listener;
+use socrates::module::*;
+use socrates::Error;
+
+use std::time::Duration;
+
+#[test]
+fn update_keeps_the_id_and_restarts_active_modules() {
+    let dir = TestDir::new("update-active");
+    let container = Container::new();
+    container.set_data_root(dir.path().join("data").to_str().unwrap());
+    let v1 = copy_fixture(&dir, "solo-v1", "solo-v1.so");
+    let v2 = copy_fixture(&dir, "solo-v2", "solo-v2.so");
+
+    let id = container.install(&v1).unwrap()[0];
+    container.start(id).unwrap();
+    let data_file = dir.path().join("data").join(id.to_string()).join("kept");
+    std::fs::create_dir_all(data_file.parent().unwrap()).unwrap();
+    std::fs::write(&data_file, "data").unwrap();
+    let recorder = container
+        .register_module_listener(Listener::new(Recorder::default()))
+        .unwrap();
+
+    container.update(id, &v2).unwrap();
+
+    // A library declaring a single module may rename it.
+    assert_eq!(container.get_manifest(id).unwrap().name, "renamed");
+    assert_eq!(container.modules().len(), 1);
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Active);
+    assert_eq!(
+        recorder.take(),
+        vec![
+            ModuleEvent::ModuleStopping(id),
+            ModuleEvent::ModuleStopped(id),
+            ModuleEvent::ModuleUpdated(id),
+            ModuleEvent::ModuleResolved(id),
+            ModuleEvent::ModuleStarting(id),
+            ModuleEvent::ModuleStarted(id),
+        ]
+    );
+    assert_eq!(std::fs::read_to_string(&data_file).unwrap(), "data");
+}
+
+#[test]
+fn reload_loads_the_changed_file_and_leaves_stopped_modules_stopped() {
+    let dir = TestDir::new("reload");
+    let container = Container::new();
+    let path = copy_fixture(&dir, "solo-v1", "solo.so");
+
+    let id = container.install(&path).unwrap()[0];
+    std::fs::remove_file(&path).unwrap();
+    std::fs::copy(fixture("solo-v2"), &path).unwrap();
+    container.reload(id).unwrap();
+
+    assert_eq!(container.get_manifest(id).unwrap().name, "renamed");
+    assert_eq!(container.get_state(id).unwrap(), DynamodState::Resolved);
+}
+
+#[test]
+fn update_refuses_a_library_missing_a_sibling() {
+    let dir = TestDir::new("update-missing-sibling");
+    let container = Container::new();
+    let pair = copy_fixture(&dir, "pair-v1", "pair.so");
+    let solo = copy_fixture(&dir, "solo-v1", "solo.so");
+
+    let ids = container.install(&pair).unwrap();
+    container.start(ids[0]).unwrap();
+
+    match container.update(ids[0], &solo) {
+        Err(Error::InvalidManifest(_)) => (),
+        other => panic!("expected InvalidManifest, got {:?}", other),
+    }
+    assert_eq!(container.get_manifest(ids[0]).unwrap().name, "alpha");
+    assert_eq!(container.get_state(ids[0]).unwrap(), DynamodState::Active);
+}
+
+#[test]
+fn update_swaps_no_module_while_a_sibling_is_stopping() {
+    let dir = TestDir::new("update-stopping-sibling");
+    let container = Container::new();
+    container.set_data_root(dir.path().join("data").to_str().unwrap());
+    container.set_stop_timeout(Some(Duration::from_millis(50)));
+    let path = copy_fixture(&dir, "stuck", "stuck.so");
+
+    let ids = container.install(&path).unwrap();
+    let (stuck, free) = (ids[0], ids[1]);
+    container.start(stuck).unwrap();
+    container.start(free).unwrap();
+    assert!(container.stop(stuck).is_err());
+    let recorder = container
+        .register_module_listener(Listener::new(Recorder::default()))
+        .unwrap();
+
+    match container.update(free, &path) {
+        Err(Error::InvalidModuleState(id, DynamodState::Stopping)) => assert_eq!(id, stuck),
+        other => panic!("expected InvalidModuleState, got {:?}", other),
+    }
+    assert!(!recorder.take().contains(&ModuleEvent::ModuleUpdated(free)));
+    assert_eq!(container.get_state(free).unwrap(), DynamodState::Active);
+
+    std::fs::write(
+        dir.path()
+            .join("data")
+            .join(stuck.to_string())
+            .join("release"),
+        "",
+    )
+    .unwrap();
+    wait_for_state(&container, stuck, DynamodState::Failed);
+    container.update(free, &path).unwrap();
+    assert_eq!(container.get_state(free).unwrap(), DynamodState::Active);
+}
@@ -0,0 +1,133 @@
+use crate::support::*;
+
+use ed25519_dalek::{Signer, SigningKey};
+use sha2::{Digest, Sha256};
+use socrates::module::*;
+use socrates::Error;
+
+const MODULE: &[u8] = b"not really a library";
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn verification_error(result: socrates::Result<()>) -> VerificationError {
+    match result {
+        Err(Error::VerificationFailed(_, e)) => e,
+        other => panic!("expected VerificationFailed, got {:?}", other),
+    }
+}
+
+#[test]
+fn trusted_digests_accept_only_their_files() {
+    let dir = TestDir::new("trusted-digests");
+    let path = dir.path().join("module.so");
+    let mut policy = TrustPolicy::default();
+    policy
+        .trust_digest_hex(&hex(&Sha256::digest(MODULE)))
+        .unwrap();
+
+    policy.verify(&path, MODULE).unwrap();
+    assert_eq!(
+        verification_error(policy.verify(&path, b"something else")),
+        VerificationError::UntrustedDigest(hex(&Sha256::digest(b"something else")))
+    );
+}
+
+#[test]
+fn digest_files_cannot_vouch_for_untrusted_files() {
+    let dir = TestDir::new("digest-files");
+    let path = dir.path().join("module.so");
+    let mut policy = TrustPolicy::default();
+    policy
+        .trust_digest_hex(&hex(&Sha256::digest(MODULE)))
+        .unwrap();
+
+    let other = b"something else";
+    std::fs::write(
+        dir.path().join("module.so.sha256"),
+        format!("{}  module.so\n", hex(&Sha256::digest(other))),
+    )
+    .unwrap();
+
+    match verification_error(policy.verify(&path, other)) {
+        VerificationError::UntrustedDigest(_) => {}
+        other => panic!("expected UntrustedDigest, got {:?}", other),
+    }
+}
+
+#[test]
+fn digest_files_catch_corrupted_files() {
+    let dir = TestDir::new("corrupted");
+    let path = dir.path().join("module.so");
+    let policy = TrustPolicy::default();
+
+    policy.verify(&path, MODULE).unwrap();
+
+    std::fs::write(
+        dir.path().join("module.so.sha256"),
+        hex(&Sha256::digest(MODULE)),
+    )
+    .unwrap();
+    policy.verify(&path, MODULE).unwrap();
+    match verification_error(policy.verify(&path, b"corrupted")) {
+        VerificationError::DigestMismatch { .. } => {}
+        other => panic!("expected DigestMismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn trusted_keys_accept_only_their_signatures() {
+    let dir = TestDir::new("trusted-keys");
+    let path = dir.path().join("module.so");
+    let sig_path = dir.path().join("module.so.sig");
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let other_key = SigningKey::from_bytes(&[9; 32]);
+    let mut policy = TrustPolicy::default();
+    policy
+        .trust_key_hex(&hex(key.verifying_key().as_bytes()))
+        .unwrap();
+
+    assert_eq!(
+        verification_error(policy.verify(&path, MODULE)),
+        VerificationError::MissingSignature
+    );
+
+    std::fs::write(&sig_path, other_key.sign(MODULE).to_bytes()).unwrap();
+    assert_eq!(
+        verification_error(policy.verify(&path, MODULE)),
+        VerificationError::UntrustedSignature
+    );
+
+    std::fs::write(&sig_path, key.sign(MODULE).to_bytes()).unwrap();
+    policy.verify(&path, MODULE).unwrap();
+
+    std::fs::write(&sig_path, hex(&key.sign(MODULE).to_bytes())).unwrap();
+    policy.verify(&path, MODULE).unwrap();
+}
+
+#[test]
+fn trusted_digests_and_keys_must_both_pass() {
+    let dir = TestDir::new("digests-and-keys");
+    let path = dir.path().join("module.so");
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let mut policy = TrustPolicy::default();
+    policy
+        .trust_digest_hex(&hex(&Sha256::digest(MODULE)))
+        .unwrap();
+    policy
+        .trust_key_hex(&hex(key.verifying_key().as_bytes()))
+        .unwrap();
+
+    assert_eq!(
+        verification_error(policy.verify(&path, MODULE)),
+        VerificationError::MissingSignature
+    );
+
+    std::fs::write(
+        dir.path().join("module.so.sig"),
+        key.sign(MODULE).to_bytes(),
+    )
+    .unwrap();
+    policy.verify(&path, MODULE).unwrap();
+}
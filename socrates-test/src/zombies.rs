@@ -0,0 +1,53 @@
+use crate::support::*;
+
+use socrates::module::*;
+
+const CONSUMER: &str = r#"{ "name": "consumer", "version": "1.0.0" }"#;
+
+#[test]
+fn uninstalled_libraries_are_unloaded_once_their_services_are_released() {
+    let dir = TestDir::new("zombie-uninstall");
+    let container = Container::new();
+    let path = copy_fixture(&dir, "solo-v1", "solo.so");
+    let provider = container.install(&path).unwrap()[0];
+    let consumer = container.install_static(manifest(CONSUMER), activate_thing_user);
+    container.start(provider).unwrap();
+    container.start(consumer).unwrap();
+
+    container.uninstall(provider).unwrap();
+
+    let zombies = container.get_zombies();
+    assert_eq!(zombies.len(), 1);
+    assert_eq!(zombies[0].id, provider);
+    assert_eq!(zombies[0].path, path);
+    assert_eq!(zombies[0].services.len(), 1);
+    assert_eq!(zombies[0].services[0].name, "thing");
+    assert_eq!(zombies[0].held_by, vec![consumer]);
+
+    container.stop(consumer).unwrap();
+    assert!(container.get_zombies().is_empty());
+}
+
+#[test]
+fn old_libraries_are_unloaded_once_their_users_are_refreshed() {
+    let dir = TestDir::new("zombie-update");
+    let container = Container::new();
+    let v1 = copy_fixture(&dir, "solo-v1", "solo-v1.so");
+    let v2 = copy_fixture(&dir, "solo-v2", "solo-v2.so");
+    let provider = container.install(&v1).unwrap()[0];
+    let consumer = container.install_static(manifest(CONSUMER), activate_thing_user);
+    container.start(provider).unwrap();
+    container.start(consumer).unwrap();
+
+    container.update(provider, &v2).unwrap();
+
+    let zombies = container.get_zombies();
+    assert_eq!(zombies.len(), 1);
+    assert_eq!(zombies[0].id, provider);
+    assert_eq!(zombies[0].path, v1);
+    assert_eq!(zombies[0].held_by, vec![consumer]);
+
+    assert_eq!(container.refresh().unwrap(), vec![consumer]);
+    assert!(container.get_zombies().is_empty());
+    assert_eq!(container.get_state(consumer).unwrap(), DynamodState::Active);
+}